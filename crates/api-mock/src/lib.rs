@@ -0,0 +1,251 @@
+// 本地的 HTTP 替身服务器,按路径依次回放录制好的响应(大模型对话、工具调用、博查搜索等),
+// 并记录收到的请求,测试时把各插件的接口地址指到这里即可离线运行
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// 一条预设的响应
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+    // 额外的响应头,比如跳转用的 Location
+    pub headers: Vec<(String, String)>,
+    // 不带 Content-Length,按 chunked 分块发送
+    pub chunked: bool,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            body: body.to_string(),
+            headers: vec![],
+            chunked: false,
+        }
+    }
+
+    pub fn text(status: u16, content_type: &str, body: &str) -> Self {
+        Self {
+            status,
+            content_type: content_type.to_string(),
+            body: body.to_string(),
+            headers: vec![],
+            chunked: false,
+        }
+    }
+
+    // 302 跳转到 location
+    pub fn redirect(location: &str) -> Self {
+        Self::text(302, "text/plain; charset=utf-8", "").with_header("Location", location)
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn chunked(mut self) -> Self {
+        self.chunked = true;
+        self
+    }
+
+    // 录制文件的格式: {"status": 200, "body": ...},body 是字符串时原样返回,否则按 JSON 返回
+    pub fn from_fixture(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let path = path.as_ref();
+        let fixture: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let status = fixture["status"].as_u64().unwrap_or(200) as u16;
+        Ok(match &fixture["body"] {
+            Value::String(body) => Self::text(status, "text/plain; charset=utf-8", body),
+            body => Self::json(status, body),
+        })
+    }
+}
+
+// 收到的一次请求
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Recorded {
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+
+    // 头部名称统一按小写查找
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    replies: Mutex<HashMap<String, VecDeque<Reply>>>,
+    requests: Mutex<Vec<Recorded>>,
+}
+
+pub struct MockApi {
+    addr: SocketAddr,
+    state: Arc<State>,
+}
+
+impl MockApi {
+    // 监听 127.0.0.1 上的随机端口
+    pub async fn start() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(State::default());
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        eprintln!("替身服务器处理请求失败: {:?}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, state })
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    // 同一路径的响应按加入顺序依次返回,用完后返回 404
+    pub fn enqueue(&self, path: &str, reply: Reply) {
+        self.state
+            .replies
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_default()
+            .push_back(reply);
+    }
+
+    pub fn enqueue_fixture(&self, path: &str, fixture: impl AsRef<Path>) {
+        let fixture = fixture.as_ref();
+        let reply = Reply::from_fixture(fixture)
+            .unwrap_or_else(|e| panic!("读取录制文件 {} 失败: {:?}", fixture.display(), e));
+        self.enqueue(path, reply);
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    pub fn requests_to(&self, path: &str) -> Vec<Recorded> {
+        self.requests().into_iter().filter(|request| request.path == path).collect()
+    }
+}
+
+// 一个连接只处理一个请求,响应后关闭
+async fn handle_connection(mut stream: TcpStream, state: Arc<State>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < head_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body_end = buffer.len().min(head_end + content_length);
+    let body = String::from_utf8_lossy(&buffer[head_end..body_end]).to_string();
+
+    let reply = state
+        .replies
+        .lock()
+        .unwrap()
+        .get_mut(&path)
+        .and_then(VecDeque::pop_front)
+        .unwrap_or_else(|| Reply::text(404, "text/plain; charset=utf-8", "no fixture"));
+    state.requests.lock().unwrap().push(Recorded {
+        method,
+        path,
+        headers,
+        body,
+    });
+
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nConnection: close\r\n",
+        reply.status,
+        reason(reply.status),
+        reply.content_type
+    );
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if reply.chunked {
+        head.push_str("Transfer-Encoding: chunked\r\n\r\n");
+        stream.write_all(head.as_bytes()).await?;
+        // 分成 64KB 的块,读取方提前断开时后面的写入失败也不算错
+        for chunk in reply.body.as_bytes().chunks(64 * 1024) {
+            let frame = [format!("{:x}\r\n", chunk.len()).as_bytes(), chunk, b"\r\n"].concat();
+            if stream.write_all(&frame).await.is_err() {
+                return Ok(());
+            }
+        }
+        let _ = stream.write_all(b"0\r\n\r\n").await;
+    } else {
+        head.push_str(&format!("Content-Length: {}\r\n\r\n", reply.body.len()));
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(reply.body.as_bytes()).await?;
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        302 => "Found",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
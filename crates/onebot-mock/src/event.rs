@@ -0,0 +1,147 @@
+use serde_json::{Value, json};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// OneBot v11 消息段
+pub fn text(text: &str) -> Value {
+    json!({ "type": "text", "data": { "text": text } })
+}
+
+pub fn image(file: &str) -> Value {
+    json!({ "type": "image", "data": { "file": file, "url": format!("https://example.com/{}", file) } })
+}
+
+pub fn face(id: i64) -> Value {
+    json!({ "type": "face", "data": { "id": id.to_string() } })
+}
+
+pub fn reply(message_id: i32) -> Value {
+    json!({ "type": "reply", "data": { "id": message_id.to_string() } })
+}
+
+pub fn at(qq: i64) -> Value {
+    json!({ "type": "at", "data": { "qq": qq.to_string() } })
+}
+
+// 群聊或私聊的一条消息,可以逐项修改发送者信息后再转成事件
+#[derive(Debug, Clone)]
+pub struct MessageEvent {
+    pub self_id: i64,
+    pub message_id: i32,
+    pub group_id: Option<i64>,
+    pub user_id: i64,
+    pub nickname: String,
+    pub card: String,
+    pub role: String,
+    pub segments: Vec<Value>,
+}
+
+impl MessageEvent {
+    pub fn group(self_id: i64, group_id: i64, user_id: i64, segments: Vec<Value>) -> Self {
+        Self {
+            self_id,
+            message_id: 1,
+            group_id: Some(group_id),
+            user_id,
+            nickname: format!("user{}", user_id),
+            card: String::new(),
+            role: "member".to_string(),
+            segments,
+        }
+    }
+
+    pub fn private(self_id: i64, user_id: i64, segments: Vec<Value>) -> Self {
+        Self {
+            group_id: None,
+            ..Self::group(self_id, 0, user_id, segments)
+        }
+    }
+
+    pub fn with_message_id(mut self, message_id: i32) -> Self {
+        self.message_id = message_id;
+        self
+    }
+
+    // owner / admin / member
+    pub fn with_role(mut self, role: &str) -> Self {
+        self.role = role.to_string();
+        self
+    }
+
+    // 所有文本段拼起来去掉首尾空白,没有文本段时为 None,和 kovi 的 borrow_text 一致
+    pub fn text(&self) -> Option<String> {
+        let texts: Vec<&str> = self
+            .segments
+            .iter()
+            .filter(|segment| segment["type"] == "text")
+            .filter_map(|segment| segment["data"]["text"].as_str())
+            .collect();
+        if texts.is_empty() {
+            None
+        } else {
+            Some(texts.concat().trim().to_string())
+        }
+    }
+
+    // CQ 码形式的原始消息
+    pub fn raw_message(&self) -> String {
+        self.segments.iter().map(cq_code).collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let is_group = self.group_id.is_some();
+        let mut event = json!({
+            "time": now(),
+            "self_id": self.self_id,
+            "post_type": "message",
+            "message_type": if is_group { "group" } else { "private" },
+            "sub_type": if is_group { "normal" } else { "friend" },
+            "message_id": self.message_id,
+            "user_id": self.user_id,
+            "message": self.segments,
+            "raw_message": self.raw_message(),
+            "font": 0,
+            "sender": {
+                "user_id": self.user_id,
+                "nickname": self.nickname,
+                "card": self.card,
+                "role": self.role,
+            },
+        });
+        if let Some(group_id) = self.group_id {
+            event["group_id"] = json!(group_id);
+        }
+        event
+    }
+}
+
+// 连接建立后协议端推送的生命周期事件
+pub fn lifecycle_connect(self_id: i64) -> Value {
+    json!({
+        "time": now(),
+        "self_id": self_id,
+        "post_type": "meta_event",
+        "meta_event_type": "lifecycle",
+        "sub_type": "connect",
+    })
+}
+
+fn cq_code(segment: &Value) -> String {
+    let kind = segment["type"].as_str().unwrap_or_default();
+    if kind == "text" {
+        return segment["data"]["text"].as_str().unwrap_or_default().to_string();
+    }
+
+    let params: String = segment["data"]
+        .as_object()
+        .map(|data| {
+            data.iter()
+                .map(|(key, value)| format!(",{}={}", key, value.as_str().unwrap_or_default()))
+                .collect()
+        })
+        .unwrap_or_default();
+    format!("[CQ:{}{}]", kind, params)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
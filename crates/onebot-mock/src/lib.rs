@@ -0,0 +1,354 @@
+// 本地模拟的 OneBot v11 协议端(正向 WebSocket 服务器)
+// kovi 按 kovi.conf.toml 的 [server] 连到 /event 和 /api,这里推送事件、记录并应答 API 调用,
+// 用来在没有 QQ 客户端的情况下端到端地测试插件
+pub mod event;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Notify, broadcast};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+
+pub use event::MessageEvent;
+
+// kovi 发来的一次 API 调用
+#[derive(Debug, Clone)]
+pub struct ApiCall {
+    pub action: String,
+    pub params: Value,
+    pub echo: Value,
+}
+
+impl ApiCall {
+    // send_msg / send_group_msg / send_private_msg
+    pub fn is_send(&self) -> bool {
+        self.action.starts_with("send_") && self.action.ends_with("_msg")
+    }
+
+    // 发出消息里的文本,消息可能是字符串也可能是消息段数组
+    pub fn text(&self) -> String {
+        match &self.params["message"] {
+            Value::String(text) => text.clone(),
+            Value::Array(segments) => segments
+                .iter()
+                .filter(|segment| segment["type"] == "text")
+                .filter_map(|segment| segment["data"]["text"].as_str())
+                .collect(),
+            _ => String::new(),
+        }
+    }
+
+    // 发出消息里某种类型的消息段
+    pub fn segments(&self, kind: &str) -> Vec<Value> {
+        self.params["message"]
+            .as_array()
+            .map(|segments| segments.iter().filter(|segment| segment["type"] == kind).cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+struct State {
+    self_id: i64,
+    access_token: String,
+    events: broadcast::Sender<String>,
+    event_connections: AtomicUsize,
+    api_connections: AtomicUsize,
+    next_message_id: AtomicI32,
+    // 还没被测试取走的调用
+    calls: Mutex<VecDeque<ApiCall>>,
+    call_arrived: Notify,
+    connected: Notify,
+    // 按 action 预设的返回数据,没有预设时用 default_response
+    responses: Mutex<HashMap<String, Value>>,
+    // get_msg 能查到的消息
+    messages: Mutex<HashMap<i64, Value>>,
+}
+
+pub struct MockOneBot {
+    addr: SocketAddr,
+    state: Arc<State>,
+}
+
+impl MockOneBot {
+    // 监听 127.0.0.1 上的随机端口
+    pub async fn start(self_id: i64, access_token: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (events, _) = broadcast::channel(64);
+        let state = Arc::new(State {
+            self_id,
+            access_token: access_token.to_string(),
+            events,
+            event_connections: AtomicUsize::new(0),
+            api_connections: AtomicUsize::new(0),
+            next_message_id: AtomicI32::new(1000),
+            calls: Mutex::new(VecDeque::new()),
+            call_arrived: Notify::new(),
+            connected: Notify::new(),
+            responses: Mutex::new(HashMap::new()),
+            messages: Mutex::new(HashMap::new()),
+        });
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, server_state.clone()));
+            }
+        });
+
+        Ok(Self { addr, state })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    pub fn self_id(&self) -> i64 {
+        self.state.self_id
+    }
+
+    // 指向这个服务器的 kovi.conf.toml
+    pub fn kovi_conf(&self, main_admin: i64, admins: &[i64]) -> String {
+        format!(
+            "[config]\nmain_admin = {}\nadmins = {:?}\ndebug = false\n\n[server]\nhost = \"127.0.0.1\"\nport = {}\naccess_token = \"{}\"\nsecure = false\n",
+            main_admin,
+            admins,
+            self.port(),
+            self.state.access_token
+        )
+    }
+
+    fn is_connected(&self) -> bool {
+        self.state.event_connections.load(Ordering::SeqCst) > 0 && self.state.api_connections.load(Ordering::SeqCst) > 0
+    }
+
+    // 等 kovi 把 /event 和 /api 都连上
+    pub async fn wait_connected(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let connected = self.state.connected.notified();
+            if self.is_connected() {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, connected).await.is_err() {
+                return self.is_connected();
+            }
+        }
+    }
+
+    pub fn push_event(&self, event: Value) {
+        // 没有连接时发送失败,事件直接丢弃
+        let _ = self.state.events.send(event.to_string());
+    }
+
+    pub fn push_message(&self, message: &MessageEvent) {
+        self.push_event(message.to_json());
+    }
+
+    // 群消息的快捷方式,发送者是普通成员
+    pub fn push_group_message(&self, group_id: i64, user_id: i64, segments: Vec<Value>) {
+        self.push_message(&MessageEvent::group(self.state.self_id, group_id, user_id, segments));
+    }
+
+    pub fn push_private_message(&self, user_id: i64, segments: Vec<Value>) {
+        self.push_message(&MessageEvent::private(self.state.self_id, user_id, segments));
+    }
+
+    // 预设某个 API 的返回数据
+    pub fn respond(&self, action: &str, data: Value) {
+        self.state.responses.lock().unwrap().insert(action.to_string(), data);
+    }
+
+    // 让 get_msg 能查到这条消息
+    pub fn remember_message(&self, message: &MessageEvent) {
+        let event = message.to_json();
+        let data = json!({
+            "time": event["time"],
+            "message_type": event["message_type"],
+            "message_id": message.message_id,
+            "real_id": message.message_id,
+            "sender": event["sender"],
+            "message": event["message"],
+        });
+        self.state.messages.lock().unwrap().insert(message.message_id as i64, data);
+    }
+
+    // 取走第一条满足条件的调用,超时返回 None
+    pub async fn wait_for_call<F>(&self, timeout: Duration, mut matches: F) -> Option<ApiCall>
+    where
+        F: FnMut(&ApiCall) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let arrived = self.state.call_arrived.notified();
+            {
+                let mut calls = self.state.calls.lock().unwrap();
+                if let Some(position) = calls.iter().position(&mut matches) {
+                    return calls.remove(position);
+                }
+            }
+            if tokio::time::timeout_at(deadline, arrived).await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    // 等机器人发出下一条消息
+    pub async fn wait_for_send(&self, timeout: Duration) -> Option<ApiCall> {
+        self.wait_for_call(timeout, ApiCall::is_send).await
+    }
+
+    // 丢掉目前记录的所有调用
+    pub fn clear_calls(&self) -> Vec<ApiCall> {
+        self.state.calls.lock().unwrap().drain(..).collect()
+    }
+}
+
+// 握手回调的错误类型由 tungstenite 决定
+#[allow(clippy::result_large_err)]
+async fn handle_connection(stream: TcpStream, state: Arc<State>) {
+    let mut path = String::new();
+    let expected = format!("Bearer {}", state.access_token);
+    let callback = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        path = request.uri().path().to_string();
+        // 也兼容放在查询参数里的 access_token
+        let authorized = state.access_token.is_empty()
+            || request
+                .headers()
+                .get("Authorization")
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value == expected)
+            || request
+                .uri()
+                .query()
+                .is_some_and(|query| query.split('&').any(|pair| pair == format!("access_token={}", state.access_token)));
+        if authorized {
+            Ok(response)
+        } else {
+            let mut error = ErrorResponse::new(Some("invalid access token".to_string()));
+            *error.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(error)
+        }
+    };
+
+    let ws = match tokio_tungstenite::accept_hdr_async(stream, callback).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            eprintln!("模拟协议端握手失败: {:?}", e);
+            return;
+        }
+    };
+
+    if path.ends_with("/event") {
+        serve_events(ws, state).await;
+    } else if path.ends_with("/api") {
+        serve_api(ws, state).await;
+    } else {
+        eprintln!("模拟协议端不支持的路径: {}", path);
+    }
+}
+
+type WebSocket = tokio_tungstenite::WebSocketStream<TcpStream>;
+
+async fn serve_events(ws: WebSocket, state: Arc<State>) {
+    let (mut sink, mut stream) = ws.split();
+    let mut events = state.events.subscribe();
+    if sink
+        .send(WsMessage::Text(event::lifecycle_connect(state.self_id).to_string()))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    state.event_connections.fetch_add(1, Ordering::SeqCst);
+    state.connected.notify_waiters();
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if sink.send(WsMessage::Text(event)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    state.event_connections.fetch_sub(1, Ordering::SeqCst);
+}
+
+async fn serve_api(ws: WebSocket, state: Arc<State>) {
+    let (mut sink, mut stream) = ws.split();
+    state.api_connections.fetch_add(1, Ordering::SeqCst);
+    state.connected.notify_waiters();
+
+    while let Some(Ok(message)) = stream.next().await {
+        let text = match message {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&text) else {
+            eprintln!("模拟协议端收到无法解析的请求: {}", text);
+            continue;
+        };
+
+        let call = ApiCall {
+            action: request["action"].as_str().unwrap_or_default().to_string(),
+            params: request["params"].clone(),
+            echo: request["echo"].clone(),
+        };
+        let response = respond_to(&state, &call);
+        state.calls.lock().unwrap().push_back(call);
+        state.call_arrived.notify_waiters();
+
+        if sink.send(WsMessage::Text(response.to_string())).await.is_err() {
+            break;
+        }
+    }
+    state.api_connections.fetch_sub(1, Ordering::SeqCst);
+}
+
+fn respond_to(state: &State, call: &ApiCall) -> Value {
+    let preset = state.responses.lock().unwrap().get(&call.action).cloned();
+    let data = match preset {
+        Some(data) => Some(data),
+        None => default_response(state, call),
+    };
+
+    match data {
+        Some(data) => json!({ "status": "ok", "retcode": 0, "data": data, "echo": call.echo }),
+        None => json!({ "status": "failed", "retcode": 100, "data": null, "echo": call.echo }),
+    }
+}
+
+// 常用 API 的默认应答,查不到的消息返回失败
+fn default_response(state: &State, call: &ApiCall) -> Option<Value> {
+    match call.action.as_str() {
+        "get_login_info" => Some(json!({ "user_id": state.self_id, "nickname": "mock" })),
+        "send_msg" | "send_group_msg" | "send_private_msg" => {
+            let message_id = state.next_message_id.fetch_add(1, Ordering::SeqCst);
+            Some(json!({ "message_id": message_id }))
+        }
+        "get_msg" => {
+            let id = call.params["message_id"].as_i64()?;
+            state.messages.lock().unwrap().get(&id).cloned()
+        }
+        _ => Some(Value::Null),
+    }
+}
@@ -3,10 +3,16 @@ use kovi::PluginBuilder as plugin;
 use kovi::bot::message::Message;
 use std::env;
 use std::fs;
+
+// 只有正好是“老鼠”两个字的消息才回图
+pub fn wants_mouse(text: Option<&str>) -> bool {
+    text == Some("老鼠")
+}
+
 #[kovi::plugin]
 async fn main() {
     plugin::on_msg(|event| async move {
-        if event.borrow_text() == Some("老鼠") {
+        if wants_mouse(event.borrow_text()) {
             // 读取本地图片文件
             let image_path = "assets/imgs/mouse.png";
             // 获取当前工作目录
@@ -0,0 +1,162 @@
+use kovi::tokio;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: i64,
+}
+
+// 联网搜索结果的磁盘缓存,按搜索后端、规范化后的问题和时效参数做 key,过期时间由 ttl 决定
+#[derive(Clone)]
+pub struct SearchCache {
+    path: PathBuf,
+    ttl: Duration,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl SearchCache {
+    pub fn new(path: PathBuf, ttl: Duration) -> Self {
+        Self {
+            path,
+            ttl,
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn open(&self) -> Result<Connection, Box<dyn Error + Send + Sync>> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS search_cache (
+                backend TEXT NOT NULL,
+                query TEXT NOT NULL,
+                freshness TEXT NOT NULL,
+                result TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (backend, query, freshness)
+            )",
+            params![],
+        )?;
+        Ok(conn)
+    }
+
+    pub async fn get(&self, backend: &str, query: &str, freshness: &str) -> Option<String> {
+        let cache = self.clone();
+        let backend = backend.to_string();
+        let key = normalize_query(query);
+        let freshness = freshness.to_string();
+
+        let cached = tokio::task::spawn_blocking(move || -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+            let conn = cache.open()?;
+            let oldest = now_secs()? - cache.ttl.as_secs() as i64;
+            let result = conn
+                .query_row(
+                    "SELECT result FROM search_cache
+                     WHERE backend = ?1 AND query = ?2 AND freshness = ?3 AND created_at >= ?4",
+                    params![backend, key, freshness, oldest],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(result)
+        })
+        .await;
+
+        let cached = cached.unwrap_or_else(|e| Err(e.into()));
+        match cached {
+            Ok(Some(result)) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(result)
+            }
+            Ok(None) => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Err(e) => {
+                eprintln!("读取搜索缓存失败: {:?}", e);
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    // 写入时顺便删掉已经过期的条目
+    pub async fn put(
+        &self,
+        backend: &str,
+        query: &str,
+        freshness: &str,
+        result: String,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let cache = self.clone();
+        let backend = backend.to_string();
+        let key = normalize_query(query);
+        let freshness = freshness.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = cache.open()?;
+            let now = now_secs()?;
+            conn.execute(
+                "DELETE FROM search_cache WHERE created_at < ?1",
+                params![now - cache.ttl.as_secs() as i64],
+            )?;
+            conn.execute(
+                "INSERT OR REPLACE INTO search_cache (backend, query, freshness, result, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![backend, key, freshness, result, now],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    // 清空缓存,返回删除的条数
+    pub async fn purge(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let cache = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = cache.open()?;
+            Ok(conn.execute("DELETE FROM search_cache", params![])?)
+        })
+        .await?
+    }
+
+    pub async fn stats(&self) -> Result<CacheStats, Box<dyn Error + Send + Sync>> {
+        let cache = self.clone();
+        let entries = tokio::task::spawn_blocking(move || -> Result<i64, Box<dyn Error + Send + Sync>> {
+            let conn = cache.open()?;
+            Ok(conn.query_row("SELECT COUNT(*) FROM search_cache", params![], |row| row.get(0))?)
+        })
+        .await??;
+
+        Ok(CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries,
+        })
+    }
+}
+
+fn now_secs() -> Result<i64, Box<dyn Error + Send + Sync>> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64)
+}
+
+// 忽略大小写、多余空白和句尾标点,让"今天天气怎么样?"和"今天天气怎么样"命中同一条
+fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .trim_end_matches(['?', '？', '!', '！', '.', '。', '~'])
+        .to_lowercase()
+}
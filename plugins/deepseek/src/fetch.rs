@@ -0,0 +1,329 @@
+use crate::{DeepSeekConfig, http_client_builder};
+use hyper::client::connect::dns::Name;
+use kovi::tokio;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use scraper::{ElementRef, Html, Selector};
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Host;
+
+// 正文所在的块级元素,以及需要整体跳过的页面框架
+const BLOCK_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6", "p", "li", "pre", "blockquote", "td"];
+const BOILERPLATE_TAGS: &[&str] = &["nav", "header", "footer", "aside", "script", "style", "noscript", "form"];
+// 页面太大就不读了
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub url: String,
+    pub title: String,
+    pub text: String,
+}
+
+// 网页抓取: 检查域名黑白名单,下载后提取正文并截断到预算长度
+#[derive(Clone)]
+pub struct PageFetcher {
+    client: Client,
+    guard: UrlGuard,
+    max_chars: usize,
+}
+
+impl PageFetcher {
+    pub fn new(config: &DeepSeekConfig) -> Self {
+        let guard = UrlGuard {
+            allow_domains: config.fetch_allow_domains.clone(),
+            deny_domains: config.fetch_deny_domains.clone(),
+            trusted_origins: vec![],
+        };
+        Self {
+            client: build_client(&guard),
+            guard,
+            max_chars: config.fetch_max_chars,
+        }
+    }
+
+    // 测试时放行本机的替身服务器,origin 形如 127.0.0.1:8080
+    #[cfg(test)]
+    pub(crate) fn trust_origin(mut self, origin: &str) -> Self {
+        self.guard.trusted_origins.push(origin.to_string());
+        self.client = build_client(&self.guard);
+        self
+    }
+
+    pub async fn fetch(&self, url: &str) -> Result<FetchedPage, Box<dyn Error + Send + Sync>> {
+        let (url, content_type, body) = self.download(url).await?;
+        let (title, text) = if content_type.starts_with("text/plain") {
+            (url.to_string(), collapse_whitespace(&body))
+        } else {
+            extract_readable_text(&body)
+        };
+
+        Ok(FetchedPage {
+            url: url.to_string(),
+            title,
+            text: truncate_chars(&text, self.max_chars),
+        })
+    }
+
+    // 下载网页,返回最终地址、Content-Type 和正文
+    pub async fn download(&self, url: &str) -> Result<(Url, String, String), Box<dyn Error + Send + Sync>> {
+        let url = self.check_url(url)?;
+        let (url, content_type, bytes) = self.download_bytes(url, "text/html,text/plain;q=0.9,*/*;q=0.1").await?;
+        if !content_type.starts_with("text/") && !content_type.contains("html") {
+            return Err(format!("不支持的内容类型: {}", content_type).into());
+        }
+
+        Ok((url, content_type.clone(), decode_text(&bytes, &content_type)))
+    }
+
+    // 按同样的规则下载任意内容,超过大小上限就停止读取
+    pub async fn download_bytes(&self, url: Url, accept: &str) -> Result<(Url, String, Vec<u8>), Box<dyn Error + Send + Sync>> {
+        let mut response = self.client
+            .get(url)
+            .timeout(Duration::from_secs(15))
+            .header("Accept", accept)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        if response.content_length().unwrap_or(0) as usize > MAX_BODY_BYTES {
+            return Err("页面太大".into());
+        }
+
+        let url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get("Content-Type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("text/html")
+            .to_string();
+
+        // chunked 响应没有 Content-Length,边读边数
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > MAX_BODY_BYTES {
+                return Err("页面太大".into());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok((url, content_type, body))
+    }
+
+    pub fn check_url(&self, url: &str) -> Result<Url, Box<dyn Error + Send + Sync>> {
+        let url = Url::parse(url.trim())?;
+        self.guard.check(&url)?;
+        Ok(url)
+    }
+}
+
+// 访问规则: 协议、内网地址和域名黑白名单,第一次请求和每次跳转都要检查;
+// 域名解析出的地址由 PublicOnlyResolver 在连接前检查
+#[derive(Clone)]
+struct UrlGuard {
+    allow_domains: Vec<String>,
+    deny_domains: Vec<String>,
+    trusted_origins: Vec<String>,
+}
+
+impl UrlGuard {
+    fn check(&self, url: &Url) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(format!("不支持的协议: {}", url.scheme()).into());
+        }
+        if self.is_trusted(url) {
+            return Ok(());
+        }
+
+        // host_str 里的 IPv6 地址带方括号,按 Host 分别处理
+        let host = match url.host().ok_or("链接没有域名")? {
+            Host::Ipv4(ip) if is_private_ip(IpAddr::V4(ip)) => return Err("不允许访问内网地址".into()),
+            Host::Ipv6(ip) if is_private_ip(IpAddr::V6(ip)) => return Err("不允许访问内网地址".into()),
+            Host::Ipv4(ip) => ip.to_string(),
+            Host::Ipv6(ip) => ip.to_string(),
+            Host::Domain(domain) => {
+                let domain = domain.trim_end_matches('.').to_lowercase();
+                if domain == "localhost" || domain.ends_with(".localhost") {
+                    return Err("不允许访问内网地址".into());
+                }
+                domain
+            }
+        };
+        if self.deny_domains.iter().any(|domain| domain_matches(&host, domain)) {
+            return Err(format!("域名 {} 在黑名单中", host).into());
+        }
+        if !self.allow_domains.is_empty() && !self.allow_domains.iter().any(|domain| domain_matches(&host, domain)) {
+            return Err(format!("域名 {} 不在白名单中", host).into());
+        }
+        Ok(())
+    }
+
+    fn is_trusted(&self, url: &Url) -> bool {
+        match (url.host_str(), url.port_or_known_default()) {
+            (Some(host), Some(port)) => self.trusted_origins.contains(&format!("{}:{}", host, port)),
+            _ => false,
+        }
+    }
+}
+
+// 抓取和链接预览用的客户端: 沿用插件共用的配置,跳转时重新检查目标地址,域名解析到内网时拒绝连接
+fn build_client(guard: &UrlGuard) -> Client {
+    let redirect_guard = guard.clone();
+    http_client_builder()
+        .dns_resolver(Arc::new(PublicOnlyResolver))
+        .redirect(Policy::custom(move |attempt| {
+            if attempt.previous().len() > MAX_REDIRECTS {
+                return attempt.error("跳转次数太多");
+            }
+            match redirect_guard.check(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        }))
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("创建网页抓取客户端失败, 使用不跟随跳转的默认配置: {:?}", e);
+            Client::builder()
+                .redirect(Policy::none())
+                .dns_resolver(Arc::new(PublicOnlyResolver))
+                .build()
+                .unwrap_or_default()
+        })
+}
+
+// 解析域名后检查每个地址,有一个是内网地址就拒绝,跳转到的域名同样经过这里
+pub(crate) struct PublicOnlyResolver;
+
+impl Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if addrs.iter().any(|addr| is_private_ip(addr.ip())) {
+                return Err(format!("域名 {} 解析到了内网地址", host).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+// example.com 同时匹配 example.com 和 www.example.com
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let domain = domain.trim().trim_start_matches("*.").to_lowercase();
+    !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+}
+
+pub(crate) fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            // ::ffff:127.0.0.1 这种映射地址按 IPv4 判断
+            Some(ip) => is_private_ipv4(ip),
+            None => is_private_ipv6(ip),
+        },
+    }
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // 0.0.0.0/8 和运营商级 NAT 的 100.64.0.0/10
+        || first == 0
+        || (first == 100 && (second & 0xc0) == 64)
+}
+
+fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // fc00::/7 唯一本地地址和 fe80::/10 链路本地地址
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+// 按 Content-Type 里的 charset 解码,没写时当作 UTF-8
+fn decode_text(bytes: &[u8], content_type: &str) -> String {
+    let encoding = content_type
+        .split(';')
+        .filter_map(|part| part.trim().strip_prefix("charset="))
+        .find_map(|charset| encoding_rs::Encoding::for_label(charset.trim_matches('"').as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+// 提取标题和正文: 优先 article / main,跳过导航、页眉页脚等框架
+fn extract_readable_text(html: &str) -> (String, String) {
+    let document = Html::parse_document(html);
+
+    let title = Selector::parse("title")
+        .ok()
+        .and_then(|selector| document.select(&selector).next().map(|el| collapse_whitespace(&el.text().collect::<String>())))
+        .unwrap_or_default();
+
+    let root = ["article", "main", "body"]
+        .iter()
+        .filter_map(|tag| Selector::parse(tag).ok())
+        .find_map(|selector| document.select(&selector).next());
+    let Some(root) = root else {
+        return (title, String::new());
+    };
+
+    let block_selector = Selector::parse(&BLOCK_TAGS.join(", ")).expect("valid selector");
+    let mut paragraphs: Vec<String> = vec![];
+    for element in root.select(&block_selector) {
+        if has_ancestor_in(&element, BOILERPLATE_TAGS) || has_ancestor_in(&element, BLOCK_TAGS) {
+            continue;
+        }
+        let text = collapse_whitespace(&element.text().collect::<String>());
+        if !text.is_empty() {
+            paragraphs.push(text);
+        }
+    }
+
+    // 没有段落标签时取整个根节点的文字,同样跳过脚本、样式和导航
+    let text = if paragraphs.is_empty() {
+        let text: String = root
+            .descendants()
+            .filter(|node| {
+                !node
+                    .ancestors()
+                    .filter_map(|node| node.value().as_element())
+                    .any(|el| BOILERPLATE_TAGS.contains(&el.name()))
+            })
+            .filter_map(|node| node.value().as_text().map(|text| &**text))
+            .collect();
+        collapse_whitespace(&text)
+    } else {
+        paragraphs.join("\n")
+    };
+
+    (title, text)
+}
+
+fn has_ancestor_in(element: &ElementRef, tags: &[&str]) -> bool {
+    element
+        .ancestors()
+        .filter_map(|node| node.value().as_element())
+        .any(|el| tags.contains(&el.name()))
+}
+
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub(crate) fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let truncated: String = text.chars().take(max_chars).collect();
+    format!("{}...(内容过长,已截断)", truncated)
+}
@@ -0,0 +1,221 @@
+use crate::search::{SearchHit, build_match_expression, query_grams};
+use kovi::tokio;
+use rusqlite::{Connection, params};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 每个分块的目标长度(字符数)和相邻分块的重叠长度
+const CHUNK_SIZE: usize = 500;
+const CHUNK_OVERLAP: usize = 50;
+// 问题里的三字窗口至少要命中这么多比例,才算本地知识库答得上
+const MIN_COVERAGE: f64 = 0.3;
+
+#[derive(Debug, Clone)]
+pub struct DocumentInfo {
+    pub id: i64,
+    pub title: String,
+    pub uploader: i64,
+    pub chunks: i64,
+}
+
+// 本地知识库,管理员上传的文本/markdown 分块后存进 SQLite FTS5,用 bm25 检索
+#[derive(Clone)]
+pub struct KnowledgeBase {
+    path: PathBuf,
+}
+
+impl KnowledgeBase {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn open(&self) -> Result<Connection, Box<dyn Error + Send + Sync>> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS kb_documents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                uploader INTEGER NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS kb_chunks USING fts5(
+                heading, content, doc_id UNINDEXED, tokenize = 'trigram'
+            );",
+        )?;
+        Ok(conn)
+    }
+
+    pub async fn add_document(
+        &self,
+        title: String,
+        uploader: i64,
+        text: String,
+    ) -> Result<(i64, usize), Box<dyn Error + Send + Sync>> {
+        let kb = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = kb.open()?;
+            let chunks = chunk_document(&title, &text);
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO kb_documents (title, uploader, created_at) VALUES (?1, ?2, ?3)",
+                params![title, uploader, now],
+            )?;
+            let doc_id = tx.last_insert_rowid();
+            for (heading, content) in &chunks {
+                tx.execute(
+                    "INSERT INTO kb_chunks (heading, content, doc_id) VALUES (?1, ?2, ?3)",
+                    params![heading, content, doc_id],
+                )?;
+            }
+            tx.commit()?;
+
+            Ok((doc_id, chunks.len()))
+        })
+        .await?
+    }
+
+    pub async fn list_documents(&self) -> Result<Vec<DocumentInfo>, Box<dyn Error + Send + Sync>> {
+        let kb = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = kb.open()?;
+            let mut stmt = conn.prepare(
+                "SELECT d.id, d.title, d.uploader,
+                        (SELECT COUNT(*) FROM kb_chunks c WHERE c.doc_id = d.id)
+                 FROM kb_documents d ORDER BY d.id",
+            )?;
+            let documents = stmt
+                .query_map(params![], |row| {
+                    Ok(DocumentInfo {
+                        id: row.get(0)?,
+                        title: row.get(1)?,
+                        uploader: row.get(2)?,
+                        chunks: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(documents)
+        })
+        .await?
+    }
+
+    pub async fn remove_document(&self, id: i64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let kb = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = kb.open()?;
+            // 分块和文档一起删,中途失败时不留下没有文档的分块
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM kb_chunks WHERE doc_id = ?1", params![id])?;
+            let removed = tx.execute("DELETE FROM kb_documents WHERE id = ?1", params![id])?;
+            tx.commit()?;
+            Ok(removed > 0)
+        })
+        .await?
+    }
+
+    // 检索与问题相关的段落,没有足够相关的内容时返回空
+    pub async fn retrieve(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, Box<dyn Error + Send + Sync>> {
+        let Some(match_expr) = build_match_expression(query) else {
+            return Ok(vec![]);
+        };
+        let grams = query_grams(query);
+
+        let kb = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = kb.open()?;
+            let mut stmt = conn.prepare(
+                "SELECT c.heading, c.content, d.title
+                 FROM kb_chunks c JOIN kb_documents d ON d.id = c.doc_id
+                 WHERE kb_chunks MATCH ?1
+                 ORDER BY bm25(kb_chunks) LIMIT 20",
+            )?;
+            let candidates = stmt
+                .query_map(params![match_expr], |row| {
+                    Ok(SearchHit {
+                        title: row.get(0)?,
+                        content: row.get(1)?,
+                        url: format!("知识库《{}》", row.get::<_, String>(2)?),
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(candidates
+                .into_iter()
+                .filter(|hit| coverage(&grams, &hit.content) >= MIN_COVERAGE)
+                .take(limit)
+                .collect())
+        })
+        .await?
+    }
+}
+
+fn coverage(grams: &[String], content: &str) -> f64 {
+    if grams.is_empty() {
+        return 0.0;
+    }
+    let content = content.to_lowercase();
+    let matched = grams
+        .iter()
+        .filter(|gram| content.contains(&gram.to_lowercase()))
+        .count();
+    matched as f64 / grams.len() as f64
+}
+
+// 按空行切段,再按长度合并/拆分;markdown 标题作为分块的小标题
+fn chunk_document(title: &str, text: &str) -> Vec<(String, String)> {
+    let mut chunks: Vec<(String, String)> = vec![];
+    let mut heading = title.to_string();
+    let mut current = String::new();
+
+    let flush = |chunks: &mut Vec<(String, String)>, heading: &str, current: &mut String| {
+        let content = current.trim();
+        if !content.is_empty() {
+            chunks.push((heading.to_string(), content.to_string()));
+        }
+        current.clear();
+    };
+
+    for paragraph in text.split("\n\n") {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() {
+            continue;
+        }
+
+        if let Some(h) = paragraph.lines().next().filter(|line| line.starts_with('#')) {
+            flush(&mut chunks, &heading, &mut current);
+            heading = format!("{} / {}", title, h.trim_start_matches('#').trim());
+        }
+
+        let paragraph_len = paragraph.chars().count();
+        if current.chars().count() + paragraph_len > CHUNK_SIZE {
+            flush(&mut chunks, &heading, &mut current);
+        }
+
+        if paragraph_len > CHUNK_SIZE {
+            let chars: Vec<char> = paragraph.chars().collect();
+            let mut start = 0;
+            while start < chars.len() {
+                let end = (start + CHUNK_SIZE).min(chars.len());
+                chunks.push((heading.clone(), chars[start..end].iter().collect()));
+                if end == chars.len() {
+                    break;
+                }
+                start = end - CHUNK_OVERLAP;
+            }
+        } else {
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(paragraph);
+        }
+    }
+    flush(&mut chunks, &heading, &mut current);
+
+    chunks
+}
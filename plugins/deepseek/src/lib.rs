@@ -1,13 +1,30 @@
 use kovi::Message as KoviMessage;
+use kovi::MsgEvent;
 use kovi::PluginBuilder as plugin;
 use kovi::tokio;
 use kovi::tokio::sync::Mutex;
-use reqwest::Client;
+use reqwest::{Client, ClientBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::error::Error;
 use std::sync::Arc;
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+mod cache;
+mod fetch;
+mod knowledge;
+mod preview;
+mod search;
+#[cfg(test)]
+mod tests;
+
+use cache::SearchCache;
+use fetch::PageFetcher;
+use knowledge::KnowledgeBase;
+use preview::{LinkPreviewer, find_first_url};
+use search::{SearchBackend, build_backend, format_hits};
 
 // 数据结构定义
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -39,6 +56,11 @@ struct SearchKnowledgeBaseArguments {
     query: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct FetchUrlArguments {
+    url: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct Choice {
     index: u32,
@@ -66,20 +88,6 @@ struct Usage {
     total_tokens: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct SearchResponse {
-    messages: Vec<SearchResponseMessage>,
-    summary: String,
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct SearchResponseMessage {
-    url: String,
-    title: String,
-    content: String,
-    icon: String,
-}
-
 // 配置结构体
 #[derive(Clone)]
 struct DeepSeekConfig {
@@ -87,19 +95,83 @@ struct DeepSeekConfig {
     api_url: String,
     search_api_key: String,
     search_url: String,
+    search_backend: String,
+    search_freshness: String,
+    search_cache_path: PathBuf,
+    search_cache_ttl: Duration,
+    searxng_url: String,
+    knowledge_base_path: PathBuf,
+    fetch_allow_domains: Vec<String>,
+    fetch_deny_domains: Vec<String>,
+    fetch_max_chars: usize,
+    url_preview_path: PathBuf,
 }
 
 impl DeepSeekConfig {
     fn new() -> Self {
         Self {
             api_key: env::var("DEEPSEEK_API_KEY").unwrap_or_default(),
-            api_url: "https://api.deepseek.com/chat/completions".to_string(),
+            // 接口地址可以指到兼容的代理或本地替身服务器
+            api_url: env::var("DEEPSEEK_API_URL").unwrap_or_else(|_| "https://api.deepseek.com/chat/completions".to_string()),
             search_api_key: env::var("BO_CHA_API_KEY").unwrap_or_default(),
-            search_url: "https://api.bochaai.com/v1/ai-search".to_string(),
+            search_url: env::var("BOCHA_SEARCH_URL").unwrap_or_else(|_| "https://api.bochaai.com/v1/ai-search".to_string()),
+            // bocha / searxng / local
+            search_backend: env::var("SEARCH_BACKEND").unwrap_or_else(|_| "bocha".to_string()),
+            // 博查的 freshness 参数: noLimit / oneDay / oneWeek / oneMonth / oneYear
+            search_freshness: env::var("SEARCH_FRESHNESS").unwrap_or_else(|_| "noLimit".to_string()),
+            search_cache_path: env::var("SEARCH_CACHE_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("data/deepseek/search_cache.db")),
+            search_cache_ttl: Duration::from_secs(
+                env::var("SEARCH_CACHE_TTL_SECS")
+                    .ok()
+                    .and_then(|ttl| ttl.parse().ok())
+                    .unwrap_or(6 * 60 * 60),
+            ),
+            searxng_url: env::var("SEARXNG_URL").unwrap_or_else(|_| "http://127.0.0.1:8080".to_string()),
+            knowledge_base_path: env::var("KNOWLEDGE_BASE_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("data/deepseek/knowledge.db")),
+            // 逗号分隔,白名单为空表示除黑名单外都允许
+            fetch_allow_domains: env_list("FETCH_ALLOW_DOMAINS"),
+            fetch_deny_domains: env_list("FETCH_DENY_DOMAINS"),
+            fetch_max_chars: env::var("FETCH_MAX_CHARS")
+                .ok()
+                .and_then(|max| max.parse().ok())
+                .unwrap_or(6000),
+            url_preview_path: env::var("URL_PREVIEW_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("data/deepseek/url_preview_groups.json")),
         }
     }
 }
 
+// 插件内所有 HTTP 请求共用的客户端配置,网页抓取和链接预览在这之上加防内网的解析和跳转规则
+pub(crate) fn http_client_builder() -> ClientBuilder {
+    Client::builder()
+        .user_agent(concat!("nt-qq-bot/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(120))
+}
+
+fn build_http_client() -> Client {
+    http_client_builder()
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("创建 HTTP 客户端失败, 使用默认配置: {:?}", e);
+            Client::new()
+        })
+}
+
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
 // 聊天历史管理器
 #[derive(Clone)]
 struct ChatHistoryManager {
@@ -146,50 +218,47 @@ impl ChatHistoryManager {
     }
 }
 
-// 知识库搜索服务
+// 知识库搜索服务,先查本地知识库,没有相关内容再联网搜索(联网结果走缓存)
 struct KnowledgeBaseSearcher {
-    client: Client,
-    config: DeepSeekConfig,
+    knowledge_base: KnowledgeBase,
+    backend: Box<dyn SearchBackend>,
+    cache: SearchCache,
+    freshness: String,
 }
 
 impl KnowledgeBaseSearcher {
     fn new(client: Client, config: DeepSeekConfig) -> Self {
-        Self { client, config }
+        let backend = build_backend(client, &config);
+        eprintln!("搜索后端: {}", backend.name());
+        Self {
+            knowledge_base: KnowledgeBase::new(config.knowledge_base_path.clone()),
+            backend,
+            cache: SearchCache::new(config.search_cache_path.clone(), config.search_cache_ttl),
+            freshness: config.search_freshness.clone(),
+        }
     }
 
     async fn search(&self, query: String) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let request_body = json!({
-            "query": &query,
-            "freshness": "noLimit",
-            "count": 10,
-            "answer": false,
-            "stream": false
-        });
+        match self.knowledge_base.retrieve(&query, 5).await {
+            Ok(passages) if !passages.is_empty() => return Ok(format_hits(&passages)),
+            Ok(_) => {}
+            Err(e) => eprintln!("知识库检索失败: {:?}", e),
+        }
 
-        let response = self.client
-            .post(&self.config.search_url)
-            .header("Content-Type", "application/json")
-            .header("Accept", "*/*")
-            .header("Connection", "keep-alive")
-            .header("Authorization", format!("Bearer {}", self.config.search_api_key))
-            .json(&request_body)
-            .send()
-            .await?;
+        if let Some(cached) = self.cache.get(self.backend.name(), &query, &self.freshness).await {
+            return Ok(cached);
+        }
 
-        if response.status().is_success() {
-            let response_text = response.text().await?;
-            let response_json: SearchResponse = serde_json::from_str(&response_text)?;
-            
-            let formatted_results = response_json.messages
-                .iter()
-                .map(|msg| format!("标题: {}\n内容: {}\n来源: {}\n", msg.title, msg.content, msg.url))
-                .collect::<Vec<String>>()
-                .join("\n---\n");
-
-            Ok(format!("搜索结果:\n{}", formatted_results))
-        } else {
-            Err(format!("搜索请求失败: {:?}", response.status()).into())
+        let hits = self.backend.search(&query).await?;
+        let result = format_hits(&hits);
+        // 没有结果时不缓存,下次再搜
+        if hits.is_empty() {
+            return Ok(result);
+        }
+        if let Err(e) = self.cache.put(self.backend.name(), &query, &self.freshness, result.clone()).await {
+            eprintln!("写入搜索缓存失败: {:?}", e);
         }
+        Ok(result)
     }
 }
 
@@ -199,20 +268,29 @@ struct DeepSeekService {
     config: DeepSeekConfig,
     history_manager: ChatHistoryManager,
     knowledge_searcher: KnowledgeBaseSearcher,
+    page_fetcher: PageFetcher,
+    link_previewer: LinkPreviewer,
 }
 
 impl DeepSeekService {
     fn new() -> Self {
-        let client = Client::new();
-        let config = DeepSeekConfig::new();
+        Self::with_config(DeepSeekConfig::new())
+    }
+
+    fn with_config(config: DeepSeekConfig) -> Self {
+        let client = build_http_client();
         let history_manager = ChatHistoryManager::new();
         let knowledge_searcher = KnowledgeBaseSearcher::new(client.clone(), config.clone());
+        let page_fetcher = PageFetcher::new(&config);
+        let link_previewer = LinkPreviewer::new(page_fetcher.clone(), config.url_preview_path.clone());
 
         Self {
             client,
             config,
             history_manager,
             knowledge_searcher,
+            page_fetcher,
+            link_previewer,
         }
     }
 
@@ -286,7 +364,7 @@ impl DeepSeekService {
                 "type": "function",
                 "function": {
                     "name": "search_knowledge_base",
-                    "description": "联网搜索用户提出的相关问题。",
+                    "description": "先检索本地知识库,没有相关内容时联网搜索用户提出的相关问题。",
                     "parameters": {
                         "type": "object",
                         "properties": {
@@ -299,6 +377,23 @@ impl DeepSeekService {
                     },
                     "strict": true
                 }
+            }, {
+                "type": "function",
+                "function": {
+                    "name": "fetch_url",
+                    "description": "下载用户给出的网页链接并提取正文,用于总结网页内容或回答关于该网页的问题。",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "url": {
+                                "type": "string",
+                                "description": "需要读取的网页地址,以 http:// 或 https:// 开头。"
+                            }
+                        },
+                        "required": ["url"]
+                    },
+                    "strict": true
+                }
             }]);
         } else {
             request["max_tokens"] = json!(8000);
@@ -310,22 +405,37 @@ impl DeepSeekService {
 
     async fn handle_tool_calls(&self, tool_calls: &[ToolCalls], original_messages: Vec<Message>) -> String {
         for tool_call in tool_calls {
-            if tool_call.function.name == "search_knowledge_base" {
-                if let Ok(args) = serde_json::from_str::<SearchKnowledgeBaseArguments>(&tool_call.function.arguments) {
+            let tool_result = match tool_call.function.name.as_str() {
+                "search_knowledge_base" => {
+                    let Ok(args) = serde_json::from_str::<SearchKnowledgeBaseArguments>(&tool_call.function.arguments) else {
+                        continue;
+                    };
                     match self.knowledge_searcher.search(args.query).await {
-                        Ok(search_results) => {
-                            let search_message = Message {
-                                role: "user".to_string(),
-                                content: search_results,
-                                tool_calls: None,
-                            };
-                            
-                            return Box::pin(self.chat(vec![search_message], false)).await;
-                        }
+                        Ok(search_results) => search_results,
                         Err(e) => return format!("搜索失败: {:?}", e),
                     }
                 }
-            }
+                "fetch_url" => {
+                    let Ok(args) = serde_json::from_str::<FetchUrlArguments>(&tool_call.function.arguments) else {
+                        continue;
+                    };
+                    match self.page_fetcher.fetch(&args.url).await {
+                        Ok(page) => format!("网页标题: {}\n网页地址: {}\n网页正文:\n{}", page.title, page.url, page.text),
+                        Err(e) => return format!("读取网页失败: {}", e),
+                    }
+                }
+                _ => continue,
+            };
+
+            // 带上用户原本的问题,让模型基于工具结果作答
+            let mut follow_up = original_messages;
+            follow_up.push(Message {
+                role: "user".to_string(),
+                content: tool_result,
+                tool_calls: None,
+            });
+
+            return Box::pin(self.chat(follow_up, false)).await;
         }
         "工具调用处理失败".to_string()
     }
@@ -340,16 +450,200 @@ fn remove_prefix_if_starts_with(input: &str, prefix: &str) -> Option<String> {
     }
 }
 
+// 管理员维护本地知识库
+// 知识库 添加 <标题>(正文写在下一行,或者附带 txt/md 文件)
+// 知识库 列表 / 知识库 删除 <id>
+async fn handle_knowledge_command(service: &DeepSeekService, event: &MsgEvent, command: &str) {
+    let knowledge_base = &service.knowledge_searcher.knowledge_base;
+    let (first_line, body) = command.split_once('\n').unwrap_or((command, ""));
+    let mut args = first_line.split_whitespace();
+
+    match args.next() {
+        Some("添加") => {
+            let title = args.collect::<Vec<&str>>().join(" ");
+            if title.is_empty() {
+                event.reply("用法: 知识库 添加 <标题>,正文写在下一行或附带 txt/md 文件");
+                return;
+            }
+
+            let mut text = body.trim().to_string();
+            if text.is_empty() {
+                match download_attached_file(&service.client, event).await {
+                    Ok(Some(content)) => text = content,
+                    Ok(None) => {
+                        event.reply("没有找到正文或附件");
+                        return;
+                    }
+                    Err(e) => {
+                        event.reply(format!("下载附件失败: {:?}", e));
+                        return;
+                    }
+                }
+            }
+
+            match knowledge_base.add_document(title.clone(), event.user_id, text).await {
+                Ok((id, chunks)) => event.reply(format!("已收录《{}》, 编号 {}, 共 {} 段", title, id, chunks)),
+                Err(e) => event.reply(format!("收录失败: {:?}", e)),
+            }
+        }
+        Some("列表") => match knowledge_base.list_documents().await {
+            Ok(documents) if documents.is_empty() => event.reply("知识库还是空的"),
+            Ok(documents) => {
+                let lines = documents
+                    .iter()
+                    .map(|doc| format!("{}. 《{}》 {} 段, 上传者 {}", doc.id, doc.title, doc.chunks, doc.uploader))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                event.reply(lines);
+            }
+            Err(e) => event.reply(format!("读取知识库失败: {:?}", e)),
+        },
+        Some("删除") => {
+            let Some(id) = args.next().and_then(|id| id.parse::<i64>().ok()) else {
+                event.reply("用法: 知识库 删除 <id>");
+                return;
+            };
+            match knowledge_base.remove_document(id).await {
+                Ok(true) => event.reply(format!("已删除 {}", id)),
+                Ok(false) => event.reply(format!("没有编号为 {} 的文档", id)),
+                Err(e) => event.reply(format!("删除失败: {:?}", e)),
+            }
+        }
+        _ => event.reply("用法: 知识库 添加 <标题> / 知识库 列表 / 知识库 删除 <id>"),
+    }
+}
+
+// 管理员查看/清空联网搜索缓存
+// 搜索缓存 统计 / 搜索缓存 清空
+async fn handle_cache_command(service: &DeepSeekService, event: &MsgEvent, command: &str) {
+    let cache = &service.knowledge_searcher.cache;
+
+    match command.trim() {
+        "统计" => match cache.stats().await {
+            Ok(stats) => {
+                let total = stats.hits + stats.misses;
+                let hit_rate = if total == 0 { 0.0 } else { stats.hits as f64 * 100.0 / total as f64 };
+                event.reply(format!(
+                    "缓存条目: {}\n命中: {}\n未命中: {}\n命中率: {:.1}%",
+                    stats.entries, stats.hits, stats.misses, hit_rate
+                ));
+            }
+            Err(e) => event.reply(format!("读取缓存失败: {:?}", e)),
+        },
+        "清空" => match cache.purge().await {
+            Ok(count) => event.reply(format!("已清空 {} 条搜索缓存", count)),
+            Err(e) => event.reply(format!("清空缓存失败: {:?}", e)),
+        },
+        _ => event.reply("用法: 搜索缓存 统计 / 搜索缓存 清空"),
+    }
+}
+
+// 管理员在群里开关链接预览
+// 链接预览 开启 / 链接预览 关闭
+async fn handle_preview_command(service: &DeepSeekService, event: &MsgEvent, command: &str) {
+    let Some(group_id) = event.group_id else {
+        event.reply("链接预览只能在群里设置");
+        return;
+    };
+
+    let enabled = match command.trim() {
+        "开启" => true,
+        "关闭" => false,
+        _ => {
+            event.reply("用法: 链接预览 开启 / 链接预览 关闭");
+            return;
+        }
+    };
+
+    match service.link_previewer.set_enabled(group_id, enabled).await {
+        Ok(()) => event.reply(if enabled { "已开启本群链接预览" } else { "已关闭本群链接预览" }),
+        Err(e) => event.reply(format!("保存设置失败: {:?}", e)),
+    }
+}
+
+// 读取消息里附带的 txt/md 文件,OneBot 实现需要在 file 段里给出 url
+async fn download_attached_file(client: &Client, event: &MsgEvent) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    for segment in event.message.get("file") {
+        let name = segment.data.get("file").and_then(|v| v.as_str()).unwrap_or_default();
+        if !(name.ends_with(".txt") || name.ends_with(".md")) {
+            continue;
+        }
+        if let Some(url) = segment.data.get("url").and_then(|v| v.as_str()) {
+            let text = client.get(url).send().await?.error_for_status()?.text().await?;
+            return Ok(Some(text));
+        }
+    }
+    Ok(None)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Route {
+    // ai <内容>: 带搜索的对话
+    Ai(String),
+    // chat <内容>: 不带搜索的对话
+    Chat(String),
+    // 群里普通消息里的第一个链接
+    LinkPreview { group_id: i64, url: String },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AdminRoute<'a> {
+    Knowledge(&'a str),
+    Cache(&'a str),
+    Preview(&'a str),
+}
+
+// 只看文本决定交给哪个处理函数,没有文本的消息直接忽略
+pub fn route(text: Option<&str>, group_id: Option<i64>) -> Option<Route> {
+    let text = text?;
+    if let Some(content) = remove_prefix_if_starts_with(text, "ai ") {
+        Some(Route::Ai(content))
+    } else if let Some(content) = remove_prefix_if_starts_with(text, "chat ") {
+        Some(Route::Chat(content))
+    } else if text.starts_with("链接预览") {
+        None
+    } else {
+        let url = find_first_url(text)?;
+        Some(Route::LinkPreview { group_id: group_id?, url })
+    }
+}
+
+pub fn route_admin(text: Option<&str>) -> Option<AdminRoute<'_>> {
+    let text = text?;
+    if let Some(command) = text.strip_prefix("知识库") {
+        Some(AdminRoute::Knowledge(command.trim_start()))
+    } else if let Some(command) = text.strip_prefix("搜索缓存") {
+        Some(AdminRoute::Cache(command))
+    } else {
+        text.strip_prefix("链接预览").map(AdminRoute::Preview)
+    }
+}
+
 #[kovi::plugin]
 async fn main() {
     let deepseek_service = Arc::new(DeepSeekService::new());
 
+    let admin_service = deepseek_service.clone();
+    plugin::on_admin_msg(move |event| {
+        let deepseek_service = admin_service.clone();
+        async move {
+            match route_admin(event.borrow_text()) {
+                Some(AdminRoute::Knowledge(command)) => {
+                    handle_knowledge_command(&deepseek_service, &event, command).await
+                }
+                Some(AdminRoute::Cache(command)) => handle_cache_command(&deepseek_service, &event, command).await,
+                Some(AdminRoute::Preview(command)) => handle_preview_command(&deepseek_service, &event, command).await,
+                None => {}
+            }
+        }
+    });
+
     plugin::on_msg(move |event| {
         let deepseek_service = deepseek_service.clone();
         async move {
-            if let Some(plain_text) = event.borrow_text() {
+            match route(event.borrow_text(), event.group_id) {
                 // 处理 AI 对话请求
-                if let Some(content) = remove_prefix_if_starts_with(&plain_text, "ai ") {
+                Some(Route::Ai(content)) => {
                     let user_message = Message {
                         role: "user".to_string(),
                         content: format!("[{}]: {}", 
@@ -363,7 +657,7 @@ async fn main() {
                     event.reply_and_quote(&response);
                 }
                 // 处理简单对话请求
-                else if let Some(content) = remove_prefix_if_starts_with(&plain_text, "chat ") {
+                Some(Route::Chat(content)) => {
                     let user_message = Message {
                         role: "user".to_string(),
                         content: format!("[{}]: {}", 
@@ -376,6 +670,16 @@ async fn main() {
                     let response = deepseek_service.chat(vec![user_message], false).await;
                     event.reply_and_quote(&response);
                 }
+                // 开启了链接预览的群里,普通消息带链接时回复预览卡片
+                Some(Route::LinkPreview { group_id, url })
+                    if deepseek_service.link_previewer.is_enabled(group_id).await =>
+                {
+                    match deepseek_service.link_previewer.preview(&url).await {
+                        Ok(card) => event.reply(card.to_message()),
+                        Err(e) => eprintln!("链接预览失败 {}: {:?}", url, e),
+                    }
+                }
+                Some(Route::LinkPreview { .. }) | None => {}
             }
         }
     });
@@ -0,0 +1,160 @@
+use crate::fetch::{PageFetcher, collapse_whitespace, truncate_chars};
+use reqwest::Url;
+use kovi::Message as KoviMessage;
+use kovi::tokio;
+use kovi::tokio::sync::Mutex;
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkCard {
+    pub title: String,
+    pub description: String,
+    // 封面图下载后的 base64,不让 QQ 客户端自己去访问页面给的地址
+    pub image: Option<String>,
+    pub site: String,
+}
+
+// 群聊链接预览: 开启的群里有人发链接时,回复标题、简介和 OpenGraph 封面
+#[derive(Clone)]
+pub struct LinkPreviewer {
+    fetcher: PageFetcher,
+    enabled_groups: Arc<Mutex<HashSet<i64>>>,
+    path: PathBuf,
+}
+
+impl LinkPreviewer {
+    pub fn new(fetcher: PageFetcher, path: PathBuf) -> Self {
+        let enabled_groups = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<HashSet<i64>>(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            fetcher,
+            enabled_groups: Arc::new(Mutex::new(enabled_groups)),
+            path,
+        }
+    }
+
+    pub async fn is_enabled(&self, group_id: i64) -> bool {
+        self.enabled_groups.lock().await.contains(&group_id)
+    }
+
+    pub async fn set_enabled(&self, group_id: i64, enabled: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut groups = self.enabled_groups.lock().await;
+        if enabled {
+            groups.insert(group_id);
+        } else {
+            groups.remove(&group_id);
+        }
+
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&self.path, serde_json::to_string(&*groups)?).await?;
+        Ok(())
+    }
+
+    pub async fn preview(&self, url: &str) -> Result<LinkCard, Box<dyn Error + Send + Sync>> {
+        let (url, content_type, body) = self.fetcher.download(url).await?;
+        if !content_type.contains("html") {
+            return Err(format!("不是网页: {}", content_type).into());
+        }
+
+        let mut card = parse_link_card(&body);
+        card.site = url.host_str().unwrap_or_default().to_string();
+        // og:image 可能是相对路径,按抓网页的同一套规则下载,失败时只发文字
+        let image_url = card.image.take().and_then(|image| url.join(&image).ok());
+        if let Some(image_url) = image_url {
+            match self.download_image(&image_url).await {
+                Ok(image) => card.image = Some(image),
+                Err(e) => eprintln!("下载链接预览封面失败 {}: {:?}", image_url, e),
+            }
+        }
+        if card.title.is_empty() {
+            card.title = url.to_string();
+        }
+
+        Ok(card)
+    }
+}
+
+impl LinkPreviewer {
+    async fn download_image(&self, url: &Url) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let url = self.fetcher.check_url(url.as_str())?;
+        let (_, content_type, bytes) = self.fetcher.download_bytes(url, "image/*").await?;
+        if !content_type.starts_with("image/") {
+            return Err(format!("封面不是图片: {}", content_type).into());
+        }
+        Ok(format!("base64://{}", base64::encode(bytes)))
+    }
+}
+
+impl LinkCard {
+    pub fn to_message(&self) -> KoviMessage {
+        let mut text = format!("🔗 {}", self.title);
+        if !self.description.is_empty() {
+            text.push_str(&format!("\n{}", self.description));
+        }
+        if !self.site.is_empty() {
+            text.push_str(&format!("\n—— {}", self.site));
+        }
+
+        let message = KoviMessage::new().add_text(text);
+        match &self.image {
+            Some(image) => message.add_image(image),
+            None => message,
+        }
+    }
+}
+
+fn parse_link_card(html: &str) -> LinkCard {
+    let document = Html::parse_document(html);
+
+    let meta = |selectors: &[&str]| -> Option<String> {
+        selectors.iter().find_map(|selector| {
+            let selector = Selector::parse(selector).ok()?;
+            document
+                .select(&selector)
+                .filter_map(|el| el.value().attr("content"))
+                .map(collapse_whitespace)
+                .find(|content| !content.is_empty())
+        })
+    };
+
+    let title = meta(&["meta[property=\"og:title\"]", "meta[name=\"twitter:title\"]"]).or_else(|| {
+        let selector = Selector::parse("title").ok()?;
+        document
+            .select(&selector)
+            .next()
+            .map(|el| collapse_whitespace(&el.text().collect::<String>()))
+    });
+    let description = meta(&[
+        "meta[property=\"og:description\"]",
+        "meta[name=\"description\"]",
+        "meta[name=\"twitter:description\"]",
+    ]);
+    let image = meta(&["meta[property=\"og:image\"]", "meta[name=\"twitter:image\"]"]);
+
+    LinkCard {
+        title: truncate_chars(&title.unwrap_or_default(), 60),
+        description: truncate_chars(&description.unwrap_or_default(), 120),
+        image,
+        site: String::new(),
+    }
+}
+
+// 找出消息里的第一个链接
+pub fn find_first_url(text: &str) -> Option<String> {
+    let start = [text.find("https://"), text.find("http://")].into_iter().flatten().min()?;
+    let url: String = text[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && c.is_ascii() && !matches!(c, '"' | '\'' | '<' | '>' | ')' | ']'))
+        .collect();
+
+    if url.len() > "https://".len() { Some(url) } else { None }
+}
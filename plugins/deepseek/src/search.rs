@@ -0,0 +1,244 @@
+use crate::DeepSeekConfig;
+use crate::knowledge::KnowledgeBase;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+
+// 单条搜索结果,各后端统一转换成这个结构
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub title: String,
+    pub content: String,
+    pub url: String,
+}
+
+// 搜索后端,`search_knowledge_base` 工具通过它联网或只查本地知识库
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn search(&self, query: &str) -> Result<Vec<SearchHit>, Box<dyn Error + Send + Sync>>;
+}
+
+// 把搜索结果拼成发给模型的文本
+pub fn format_hits(hits: &[SearchHit]) -> String {
+    if hits.is_empty() {
+        return "搜索结果:\n没有找到相关内容".to_string();
+    }
+
+    let formatted_results = hits
+        .iter()
+        .map(|hit| format!("标题: {}\n内容: {}\n来源: {}\n", hit.title, hit.content, hit.url))
+        .collect::<Vec<String>>()
+        .join("\n---\n");
+
+    format!("搜索结果:\n{}", formatted_results)
+}
+
+// 根据配置选择搜索后端
+pub fn build_backend(client: Client, config: &DeepSeekConfig) -> Box<dyn SearchBackend> {
+    match config.search_backend.as_str() {
+        "searxng" => Box::new(SearxngBackend::new(client, config.searxng_url.clone())),
+        "local" => Box::new(LocalIndexBackend::new(KnowledgeBase::new(config.knowledge_base_path.clone()))),
+        "bocha" => Box::new(BochaBackend::new(client, config)),
+        other => {
+            eprintln!("未知的搜索后端 {}, 使用 bocha", other);
+            Box::new(BochaBackend::new(client, config))
+        }
+    }
+}
+
+// 博查 AI 搜索
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct BochaResponse {
+    messages: Vec<BochaResponseMessage>,
+    summary: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct BochaResponseMessage {
+    url: String,
+    title: String,
+    content: String,
+    icon: String,
+}
+
+pub struct BochaBackend {
+    client: Client,
+    url: String,
+    api_key: String,
+    freshness: String,
+}
+
+impl BochaBackend {
+    pub fn new(client: Client, config: &DeepSeekConfig) -> Self {
+        Self {
+            client,
+            url: config.search_url.clone(),
+            api_key: config.search_api_key.clone(),
+            freshness: config.search_freshness.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for BochaBackend {
+    fn name(&self) -> &'static str {
+        "bocha"
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<SearchHit>, Box<dyn Error + Send + Sync>> {
+        let request_body = json!({
+            "query": query,
+            "freshness": &self.freshness,
+            "count": 10,
+            "answer": false,
+            "stream": false
+        });
+
+        let response = self.client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "*/*")
+            .header("Connection", "keep-alive")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&request_body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("搜索请求失败: {:?}", response.status()).into());
+        }
+
+        let response_text = response.text().await?;
+        let response_json: BochaResponse = serde_json::from_str(&response_text)?;
+
+        Ok(response_json
+            .messages
+            .into_iter()
+            .map(|msg| SearchHit {
+                title: msg.title,
+                content: msg.content,
+                url: msg.url,
+            })
+            .collect())
+    }
+}
+
+// 自建 SearXNG,需要在 settings.yml 里打开 json 格式
+#[derive(Debug, Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearxngResult {
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content: String,
+}
+
+pub struct SearxngBackend {
+    client: Client,
+    base_url: String,
+}
+
+impl SearxngBackend {
+    pub fn new(client: Client, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for SearxngBackend {
+    fn name(&self) -> &'static str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<SearchHit>, Box<dyn Error + Send + Sync>> {
+        let response = self.client
+            .get(format!("{}/search", self.base_url.trim_end_matches('/')))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("搜索请求失败: {:?}", response.status()).into());
+        }
+
+        let response_json: SearxngResponse = response.json().await?;
+
+        Ok(response_json
+            .results
+            .into_iter()
+            .take(10)
+            .map(|result| SearchHit {
+                title: result.title,
+                content: result.content,
+                url: result.url,
+            })
+            .collect())
+    }
+}
+
+// 只查管理员上传的本地知识库,不联网,也不需要任何 API key
+pub struct LocalIndexBackend {
+    knowledge_base: KnowledgeBase,
+}
+
+impl LocalIndexBackend {
+    pub fn new(knowledge_base: KnowledgeBase) -> Self {
+        Self { knowledge_base }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for LocalIndexBackend {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<SearchHit>, Box<dyn Error + Send + Sync>> {
+        self.knowledge_base.retrieve(query, 10).await
+    }
+}
+
+// 把问题切成三字窗口再用 OR 连接,交给 bm25 排序
+pub(crate) fn build_match_expression(query: &str) -> Option<String> {
+    let mut grams = query_grams(query);
+    if grams.is_empty() {
+        return None;
+    }
+
+    grams.truncate(64);
+    Some(
+        grams
+            .iter()
+            .map(|gram| format!("\"{}\"", gram))
+            .collect::<Vec<String>>()
+            .join(" OR "),
+    )
+}
+
+// trigram 分词下,少于三个字的词没法匹配,直接丢掉
+pub(crate) fn query_grams(query: &str) -> Vec<String> {
+    let mut grams: Vec<String> = vec![];
+
+    for term in query.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = term.chars().collect();
+        for window in chars.windows(3) {
+            let gram: String = window.iter().collect();
+            if !grams.contains(&gram) {
+                grams.push(gram);
+            }
+        }
+    }
+
+    grams
+}
@@ -0,0 +1,347 @@
+// 用本地替身服务器回放录制的接口响应,离线测试对话、工具调用和出错时的回复
+use super::*;
+use api_mock::{MockApi, Reply};
+use std::path::Path;
+use tempfile::TempDir;
+
+const CHAT_PATH: &str = "/chat/completions";
+const SEARCH_PATH: &str = "/v1/ai-search";
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn user_message(content: &str) -> Message {
+    Message {
+        role: "user".to_string(),
+        content: content.to_string(),
+        tool_calls: None,
+    }
+}
+
+// 测试用的完整配置,不读环境变量;数据文件都放在临时目录
+fn test_config(api_url: String, search_url: String, dir: &TempDir) -> DeepSeekConfig {
+    DeepSeekConfig {
+        api_key: "test-key".to_string(),
+        api_url,
+        search_api_key: "search-key".to_string(),
+        search_url,
+        search_backend: "bocha".to_string(),
+        search_freshness: "noLimit".to_string(),
+        search_cache_path: dir.path().join("search_cache.db"),
+        search_cache_ttl: Duration::from_secs(6 * 60 * 60),
+        searxng_url: "http://127.0.0.1:9".to_string(),
+        knowledge_base_path: dir.path().join("knowledge.db"),
+        fetch_allow_domains: vec![],
+        fetch_deny_domains: vec![],
+        fetch_max_chars: 6000,
+        url_preview_path: dir.path().join("url_preview_groups.json"),
+    }
+}
+
+// 接口都指到替身服务器
+fn service(api: &MockApi, dir: &TempDir) -> DeepSeekService {
+    DeepSeekService::with_config(test_config(api.url(CHAT_PATH), api.url(SEARCH_PATH), dir))
+}
+
+async fn setup() -> (MockApi, TempDir) {
+    let api = MockApi::start().await.expect("start mock api");
+    let dir = tempfile::tempdir().expect("create temp dir");
+    (api, dir)
+}
+
+#[tokio::test]
+async fn plain_chat_returns_model_answer() {
+    let (api, dir) = setup().await;
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_answer.json"));
+    let service = service(&api, &dir);
+
+    let answer = service.chat(vec![user_message("今天做什么好")], false).await;
+    assert_eq!(answer, "今天适合出门散步。");
+
+    let requests = api.requests_to(CHAT_PATH);
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].header("authorization"), Some("Bearer test-key"));
+    let body = requests[0].json();
+    assert_eq!(body["model"], "deepseek-chat");
+    assert!(body.get("tools").is_none());
+    assert_eq!(body["messages"].as_array().unwrap().last().unwrap()["content"], "今天做什么好");
+}
+
+#[tokio::test]
+async fn tool_chat_keeps_history() {
+    let (api, dir) = setup().await;
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_answer.json"));
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_answer.json"));
+    let service = service(&api, &dir);
+
+    service.chat(vec![user_message("[小明]: 第一句")], true).await;
+    service.chat(vec![user_message("[小明]: 第二句")], true).await;
+
+    let requests = api.requests_to(CHAT_PATH);
+    assert!(requests[0].json()["tools"].is_array());
+    let second: Vec<String> = requests[1].json()["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["content"].as_str().unwrap_or_default().to_string())
+        .collect();
+    assert!(second.contains(&"[小明]: 第一句".to_string()));
+}
+
+#[tokio::test]
+async fn search_tool_call_answers_with_search_results() {
+    let (api, dir) = setup().await;
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_tool_call_search.json"));
+    api.enqueue_fixture(SEARCH_PATH, fixture("bocha_search.json"));
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_after_search.json"));
+    let service = service(&api, &dir);
+
+    let answer = service.chat(vec![user_message("kovi 是什么")], true).await;
+    assert_eq!(answer, "kovi 是一个 Rust 写的 OneBot 机器人框架。");
+
+    let searches = api.requests_to(SEARCH_PATH);
+    assert_eq!(searches.len(), 1);
+    assert_eq!(searches[0].header("authorization"), Some("Bearer search-key"));
+    assert_eq!(searches[0].json()["query"], "kovi 框架");
+
+    // 第二次对话带上了原问题和搜索结果,并且不再提供工具
+    let follow_up = api.requests_to(CHAT_PATH)[1].json();
+    assert!(follow_up.get("tools").is_none());
+    let messages = follow_up["messages"].as_array().unwrap();
+    let last = messages.last().unwrap()["content"].as_str().unwrap();
+    assert!(last.starts_with("搜索结果:"));
+    assert!(last.contains("https://github.com/Threkork/Kovi"));
+    assert_eq!(messages[messages.len() - 2]["content"], "kovi 是什么");
+}
+
+#[tokio::test]
+async fn repeated_search_uses_cache() {
+    let (api, dir) = setup().await;
+    for _ in 0..2 {
+        api.enqueue_fixture(CHAT_PATH, fixture("chat_tool_call_search.json"));
+        api.enqueue_fixture(CHAT_PATH, fixture("chat_after_search.json"));
+    }
+    api.enqueue_fixture(SEARCH_PATH, fixture("bocha_search.json"));
+    let service = service(&api, &dir);
+
+    service.chat(vec![user_message("kovi 是什么")], true).await;
+    let answer = service.chat(vec![user_message("kovi 是什么")], true).await;
+    assert_eq!(answer, "kovi 是一个 Rust 写的 OneBot 机器人框架。");
+    assert_eq!(api.requests_to(SEARCH_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn search_failure_is_reported() {
+    let (api, dir) = setup().await;
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_tool_call_search.json"));
+    api.enqueue_fixture(SEARCH_PATH, fixture("bocha_rate_limited.json"));
+    let service = service(&api, &dir);
+
+    let answer = service.chat(vec![user_message("kovi 是什么")], true).await;
+    assert!(answer.starts_with("搜索失败"), "{}", answer);
+    assert!(answer.contains("429"), "{}", answer);
+    assert_eq!(api.requests_to(CHAT_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn fetch_tool_refuses_private_address() {
+    let (api, dir) = setup().await;
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_tool_call_fetch.json"));
+    let service = service(&api, &dir);
+
+    let answer = service.chat(vec![user_message("看看这个后台")], true).await;
+    assert!(answer.starts_with("读取网页失败"), "{}", answer);
+    assert_eq!(api.requests_to(CHAT_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn api_errors_are_reported() {
+    let (api, dir) = setup().await;
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_unauthorized.json"));
+    api.enqueue_fixture(CHAT_PATH, fixture("chat_no_choices.json"));
+    api.enqueue(CHAT_PATH, Reply::text(502, "text/html", "<html>Bad Gateway</html>"));
+    let service = service(&api, &dir);
+
+    let answer = service.chat(vec![user_message("你好")], false).await;
+    assert!(answer.starts_with("解析响应失败"), "{}", answer);
+    assert!(answer.contains("Authentication Fails"), "{}", answer);
+
+    let answer = service.chat(vec![user_message("你好")], false).await;
+    assert_eq!(answer, "未收到有效回复");
+
+    let answer = service.chat(vec![user_message("你好")], false).await;
+    assert!(answer.contains("Bad Gateway"), "{}", answer);
+}
+
+#[tokio::test]
+async fn unreachable_api_is_reported() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let service = DeepSeekService::with_config(test_config(
+        "http://127.0.0.1:9/chat/completions".to_string(),
+        "http://127.0.0.1:9/v1/ai-search".to_string(),
+        &dir,
+    ));
+
+    let answer = service.chat(vec![user_message("你好")], false).await;
+    assert!(answer.starts_with("请求失败"), "{}", answer);
+}
+
+#[tokio::test]
+async fn local_backend_searches_knowledge_base() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let knowledge_base = KnowledgeBase::new(dir.path().join("knowledge.db"));
+    knowledge_base
+        .add_document("群规".to_string(), 1, "本群禁止在工作日晚上十点后发语音消息。".to_string())
+        .await
+        .expect("add document");
+
+    let backend = search::LocalIndexBackend::new(knowledge_base);
+    let hits = backend.search("晚上十点后发语音").await.expect("search");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].url, "知识库《群规》");
+    assert!(backend.search("今天天气怎么样").await.expect("search").is_empty());
+}
+
+#[tokio::test]
+async fn search_cache_is_keyed_by_backend_and_prunes_expired_rows() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("search_cache.db");
+    let cache = SearchCache::new(path.clone(), Duration::from_secs(60));
+
+    cache.put("bocha", "kovi 是什么", "noLimit", "博查的结果".to_string()).await.expect("put");
+    assert_eq!(cache.get("bocha", "Kovi 是什么?", "noLimit").await.as_deref(), Some("博查的结果"));
+    assert_eq!(cache.get("searxng", "kovi 是什么", "noLimit").await, None);
+
+    // 插一条早已过期的,下一次写入时会被清掉
+    rusqlite::Connection::open(&path)
+        .expect("open cache db")
+        .execute(
+            "INSERT INTO search_cache (backend, query, freshness, result, created_at) VALUES ('bocha', '旧问题', 'noLimit', '旧结果', 0)",
+            rusqlite::params![],
+        )
+        .expect("insert expired row");
+    assert_eq!(cache.stats().await.expect("stats").entries, 2);
+    cache.put("searxng", "kovi 是什么", "noLimit", "searxng 的结果".to_string()).await.expect("put");
+    assert_eq!(cache.stats().await.expect("stats").entries, 2);
+    assert_eq!(cache.get("searxng", "kovi 是什么", "noLimit").await.as_deref(), Some("searxng 的结果"));
+}
+
+#[tokio::test]
+async fn fetch_refuses_private_ipv6_and_mapped_addresses() {
+    let (api, dir) = setup().await;
+    let service = service(&api, &dir);
+
+    for url in ["http://[::1]/", "http://[fd00::1]/admin", "http://[fe80::1]/", "http://[::ffff:127.0.0.1]/", "http://0.0.0.0/"] {
+        let error = service.page_fetcher.fetch(url).await.expect_err(url).to_string();
+        assert!(error.contains("内网"), "{}: {}", url, error);
+    }
+    assert!(api.requests().is_empty());
+}
+
+#[tokio::test]
+async fn fetch_rechecks_redirect_targets() {
+    let (api, dir) = setup().await;
+    let origin = api.url("").trim_start_matches("http://").to_string();
+    let fetcher = service(&api, &dir).page_fetcher.clone().trust_origin(&origin);
+
+    api.enqueue("/page", Reply::redirect("http://127.0.0.1:1/admin"));
+    let error = fetcher.fetch(&api.url("/page")).await.expect_err("redirect to loopback");
+    assert!(format!("{:?}", error).contains("内网"), "{:?}", error);
+
+    // 放行的地址之间跳转照常跟随
+    api.enqueue("/moved", Reply::redirect(&api.url("/article")));
+    api.enqueue("/article", Reply::text(200, "text/html; charset=utf-8", "<title>文章</title><p>正文</p>"));
+    let page = fetcher.fetch(&api.url("/moved")).await.expect("follow redirect");
+    assert_eq!(page.title, "文章");
+    assert_eq!(page.url, api.url("/article"));
+}
+
+#[tokio::test]
+async fn fetch_keeps_body_text_and_drops_scripts_styles_and_navigation() {
+    let (api, dir) = setup().await;
+    let origin = api.url("").trim_start_matches("http://").to_string();
+    let fetcher = service(&api, &dir).page_fetcher.clone().trust_origin(&origin);
+
+    let html = std::fs::read_to_string(fixture("article.html")).expect("read fixture");
+    api.enqueue("/article", Reply::text(200, "text/html; charset=utf-8", &html));
+    let page = fetcher.fetch(&api.url("/article")).await.expect("fetch article");
+    assert_eq!(page.title, "新版本发布说明");
+    assert_eq!(page.text, "新版本发布\n这次更新 修复了 搜索缓存的问题。\n支持链接预览\n抓取网页时只保留正文");
+
+    // 没有段落标签时取整个 body 的文字,同样不要脚本、样式和导航
+    let html = std::fs::read_to_string(fixture("plain_body.html")).expect("read fixture");
+    api.enqueue("/plain", Reply::text(200, "text/html; charset=utf-8", &html));
+    let page = fetcher.fetch(&api.url("/plain")).await.expect("fetch plain page");
+    assert_eq!(page.text, "没有段落标签的 正文");
+}
+
+#[tokio::test]
+async fn fetch_stops_reading_oversized_chunked_body() {
+    let (api, dir) = setup().await;
+    let origin = api.url("").trim_start_matches("http://").to_string();
+    let fetcher = service(&api, &dir).page_fetcher.clone().trust_origin(&origin);
+
+    let body = format!("<p>{}</p>", "字".repeat(1024 * 1024));
+    api.enqueue("/huge", Reply::text(200, "text/html", &body).chunked());
+    let error = fetcher.fetch(&api.url("/huge")).await.expect_err("oversized body");
+    assert_eq!(error.to_string(), "页面太大");
+}
+
+#[tokio::test]
+async fn resolver_refuses_names_pointing_at_private_addresses() {
+    use reqwest::dns::Resolve;
+    let name = "localhost".parse().expect("dns name");
+    let result = fetch::PublicOnlyResolver.resolve(name).await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn first_url_is_the_earliest_link() {
+    assert_eq!(
+        find_first_url("先看 http://a.example/1 再看 https://b.example/2").as_deref(),
+        Some("http://a.example/1")
+    );
+    assert_eq!(find_first_url("https://b.example/2 和 http://a.example/1").as_deref(), Some("https://b.example/2"));
+    assert_eq!(find_first_url("没有链接"), None);
+}
+
+#[tokio::test]
+async fn link_preview_downloads_cover_with_fetch_rules() {
+    let (api, dir) = setup().await;
+    let origin = api.url("").trim_start_matches("http://").to_string();
+    let fetcher = service(&api, &dir).page_fetcher.clone().trust_origin(&origin);
+    let previewer = LinkPreviewer::new(fetcher, dir.path().join("url_preview_groups.json"));
+
+    // 内网封面不下载,只发文字
+    api.enqueue(
+        "/internal",
+        Reply::text(
+            200,
+            "text/html",
+            r#"<meta property="og:title" content="内网封面"><meta property="og:image" content="http://10.0.0.1/a.png">"#,
+        ),
+    );
+    let card = previewer.preview(&api.url("/internal")).await.expect("preview");
+    assert_eq!(card.title, "内网封面");
+    assert_eq!(card.image, None);
+
+    // 相对路径的封面按页面地址补全后下载成 base64
+    api.enqueue(
+        "/page",
+        Reply::text(200, "text/html", r#"<title>有封面</title><meta property="og:image" content="/cover.png">"#),
+    );
+    api.enqueue("/cover.png", Reply::text(200, "image/png", "png"));
+    let card = previewer.preview(&api.url("/page")).await.expect("preview");
+    assert_eq!(card.image.as_deref(), Some("base64://cG5n"));
+    // 页面和封面都用插件共用的客户端配置请求
+    let user_agent = concat!("nt-qq-bot/", env!("CARGO_PKG_VERSION"));
+    for path in ["/page", "/cover.png"] {
+        assert_eq!(api.requests_to(path)[0].header("User-Agent"), Some(user_agent));
+    }
+
+    previewer.set_enabled(42, true).await.expect("enable preview");
+    assert!(previewer.is_enabled(42).await);
+    let saved = std::fs::read_to_string(dir.path().join("url_preview_groups.json")).expect("saved groups");
+    assert_eq!(saved, "[42]");
+}
@@ -0,0 +1,211 @@
+use crate::images::{ImageStore, MAX_IMAGE_BYTES};
+use crate::store::{NewQuote, QuoteStore};
+use kovi::tokio;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tar::EntryType;
+
+// 导出的 tar 包: manifest.json 记录语录,images/ 下放图片文件
+const MANIFEST: &str = "manifest.json";
+const IMAGE_PREFIX: &str = "images/";
+// 格式有不兼容的改动时加一,导入时拒绝认不出的新格式
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    // 从哪个群导出的,导入时可以换成别的群
+    group_id: i64,
+    exported_at: String,
+    quotes: Vec<NewQuote>,
+}
+
+pub struct ExportSummary {
+    quotes: usize,
+    images: usize,
+    // 数据库里有记录但图片文件已经不在了
+    missing_images: usize,
+}
+
+impl fmt::Display for ExportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "导出 {} 条语录, {} 张图片", self.quotes, self.images)?;
+        if self.missing_images > 0 {
+            write!(f, ", {} 张图片文件丢失", self.missing_images)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct ImportSummary {
+    source_group: i64,
+    total: usize,
+    added: usize,
+    // 图片不在包里,没法导入
+    missing_images: usize,
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "从群 {} 的语录本导入 {} 条, 新增 {} 条",
+            self.source_group, self.total, self.added
+        )?;
+        let duplicated = self.total - self.added - self.missing_images;
+        if duplicated > 0 {
+            write!(f, ", {} 条已经有了", duplicated)?;
+        }
+        if self.missing_images > 0 {
+            write!(f, ", {} 条缺少图片被跳过", self.missing_images)?;
+        }
+        Ok(())
+    }
+}
+
+// 把一个群的语录和用到的图片打成 tar 包
+pub async fn export(
+    store: &QuoteStore,
+    images: &ImageStore,
+    group_id: i64,
+    path: &Path,
+    exported_at: String,
+) -> Result<ExportSummary, Box<dyn Error + Send + Sync>> {
+    let quotes = store.export_quotes(group_id).await?;
+    let files: Vec<(String, PathBuf)> = quotes
+        .iter()
+        .filter(|quote| !quote.image_file.is_empty())
+        .map(|quote| quote.image_file.clone())
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .map(|file| {
+            let source = images.path(&file);
+            (file, source)
+        })
+        .collect();
+    let manifest = Manifest {
+        format: FORMAT_VERSION,
+        group_id,
+        exported_at,
+        quotes,
+    };
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut builder = tar::Builder::new(File::create(&path)?);
+
+        let json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, MANIFEST, json.as_slice())?;
+
+        let mut summary = ExportSummary {
+            quotes: manifest.quotes.len(),
+            images: 0,
+            missing_images: 0,
+        };
+        for (file, source) in files {
+            if source.exists() {
+                builder.append_path_with_name(&source, format!("{}{}", IMAGE_PREFIX, file))?;
+                summary.images += 1;
+            } else {
+                summary.missing_images += 1;
+            }
+        }
+        builder.into_inner()?;
+        Ok(summary)
+    })
+    .await?
+}
+
+// 把 tar 包里的语录导入到指定的群,图片按文件名合并进图片目录
+pub async fn import(
+    store: &QuoteStore,
+    images: &ImageStore,
+    group_id: i64,
+    path: &Path,
+) -> Result<ImportSummary, Box<dyn Error + Send + Sync>> {
+    let path = path.to_path_buf();
+    let unpack_images = images.clone();
+    let manifest = tokio::task::spawn_blocking(move || -> Result<Manifest, Box<dyn Error + Send + Sync>> {
+        let mut archive = tar::Archive::new(File::open(&path)?);
+        let mut manifest = None;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            // 只认普通文件,符号链接、硬链接、目录等一律跳过
+            if entry.header().entry_type() != EntryType::Regular {
+                continue;
+            }
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if name == MANIFEST {
+                manifest = Some(serde_json::from_reader::<_, Manifest>(&mut entry)?);
+            } else if let Some(file) = name.strip_prefix(IMAGE_PREFIX).filter(|file| is_file_name(file)) {
+                if entry.size() > MAX_IMAGE_BYTES as u64 {
+                    continue;
+                }
+                let mut bytes = Vec::new();
+                (&mut entry).take(MAX_IMAGE_BYTES as u64).read_to_end(&mut bytes)?;
+                // 图片按内容哈希命名,对不上的当作损坏的文件
+                if file_stem(file) != Some(format!("{:x}", Sha256::digest(&bytes)).as_str()) {
+                    continue;
+                }
+                let target = unpack_images.path(file);
+                if !target.exists() {
+                    if let Some(parent) = target.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&target, &bytes)?;
+                }
+            }
+        }
+
+        let manifest: Manifest = manifest.ok_or("压缩包里没有 manifest.json")?;
+        if manifest.format > FORMAT_VERSION {
+            return Err(format!("不认识的语录包格式版本 {}", manifest.format).into());
+        }
+        Ok(manifest)
+    })
+    .await??;
+
+    let total = manifest.quotes.len();
+    // 文件名来自压缩包,只接受图片目录里的普通文件名,并且文件名就是语录记录的图片哈希
+    let quotes: Vec<NewQuote> = manifest
+        .quotes
+        .into_iter()
+        .filter(|quote| {
+            quote.image_file.is_empty()
+                || (is_file_name(&quote.image_file)
+                    && file_stem(&quote.image_file) == Some(quote.image_hash.as_str())
+                    && images.path(&quote.image_file).exists())
+        })
+        .collect();
+    let missing_images = total - quotes.len();
+    let added = store.import_quotes(group_id, quotes).await?;
+
+    Ok(ImportSummary {
+        source_group: manifest.group_id,
+        total,
+        added,
+        missing_images,
+    })
+}
+
+fn file_stem(name: &str) -> Option<&str> {
+    Path::new(name).file_stem().and_then(|stem| stem.to_str())
+}
+
+// 不含目录的文件名,防止压缩包往图片目录外面写
+fn is_file_name(name: &str) -> bool {
+    Path::new(name).file_name().is_some_and(|file| file == name)
+}
@@ -0,0 +1,90 @@
+use kovi::tokio;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::path::PathBuf;
+
+// 语录图片最大 10MB,导入语录包时也按这个限制
+pub const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+// 下载好的图片和它的 sha256
+pub struct DownloadedImage {
+    pub bytes: Vec<u8>,
+    pub hash: String,
+    pub extension: &'static str,
+}
+
+// 图片按内容哈希命名存放,同一张图只存一份
+#[derive(Clone)]
+pub struct ImageStore {
+    client: Client,
+    dir: PathBuf,
+}
+
+impl ImageStore {
+    pub fn new(client: Client, dir: PathBuf) -> Self {
+        Self { client, dir }
+    }
+
+    pub async fn download(&self, url: &str) -> Result<DownloadedImage, Box<dyn Error + Send + Sync>> {
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        if response.content_length().unwrap_or(0) as usize > MAX_IMAGE_BYTES {
+            return Err("图片太大".into());
+        }
+
+        // chunked 响应没有 Content-Length,边读边数,超过上限就不再往下读
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if bytes.len() + chunk.len() > MAX_IMAGE_BYTES {
+                return Err("图片太大".into());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        let extension = image_extension(&bytes).ok_or("不是支持的图片格式")?;
+
+        Ok(DownloadedImage {
+            hash: format!("{:x}", Sha256::digest(&bytes)),
+            bytes,
+            extension,
+        })
+    }
+
+    // 写入图片目录,返回相对图片目录的文件名
+    pub async fn save(&self, image: &DownloadedImage) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let file_name = format!("{}.{}", image.hash, image.extension);
+        let path = self.dir.join(&file_name);
+        if !path.exists() {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(&path, &image.bytes).await?;
+        }
+        Ok(file_name)
+    }
+
+    // 图片文件的完整路径,导出导入时用
+    pub fn path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
+
+    // 读出存好的图片,发送时用 base64,协议端不在本机也能收到
+    pub async fn load_base64(&self, file_name: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let bytes = tokio::fs::read(self.dir.join(file_name)).await?;
+        Ok(format!("base64://{}", base64::encode(bytes)))
+    }
+}
+
+// 按文件头判断图片格式
+fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("gif")
+    } else if bytes.len() > 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("bmp")
+    } else {
+        None
+    }
+}
@@ -1,50 +1,872 @@
-// use kovi::PluginBuilder as plugin;
-// use rusqlite::{params, Connection, Result};
-// use rusqlite::types::Value;
-
-// #[kovi::plugin]
-// async fn main() {
-//     let conn = Connection::open("my_database.db")?;
-
-//     // 创建表
-//     conn.execute(
-//         "CREATE TABLE IF NOT EXISTS users (
-//             id INTEGER PRIMARY KEY AUTOINCREMENT,
-//             name TEXT NOT NULL,
-//             age INTEGER NOT NULL
-//         )",
-//         params![],
-//     )?;
-
-//     // 插入数据
-//     conn.execute(
-//         "INSERT INTO users (name, age) VALUES (?1, ?2)",
-//         params!["Alice", 30],
-//     )?;
-
-
-//     plugin::on_msg(|event| async move {
-//         if event.raw_message.contains("[CQ:at,qq=3939271104]")
-//                 && event.borrow_text().unwrap().to_string().starts_with("语录")
-//             {
-//                 let message = event
-//                     .original_json
-//                     .get("message")
-//                     .and_then(|m| m.as_array()).unwrap();
-
-//                 let msg_url = for msg in message.into_iter() {
-//                     println!("here:{:?}", msg);
-//                     if msg.get("type") == Some(&serde_json::Value::String("image".to_string())) {
-//                         return msg
-//                             .get("data")
-//                             .and_then(|data| data.get("url"))
-//                             .unwrap_or(&serde_json::Value::Null)
-//                             .to_string();
-//                     }
-//                 };
-//                 println!("here:{:?}", msg_url);
-//                 event.reply(format!("{:?}", msg_url));
-//             }
-//             return "".to_string();
-//     });
-// }
+use kovi::Message as KoviMessage;
+use kovi::MsgEvent;
+use kovi::PluginBuilder as plugin;
+use kovi::RuntimeBot;
+use kovi::chrono::{Local, TimeZone};
+use kovi::serde_json::{Value, json};
+use kovi::tokio;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod archive;
+mod images;
+mod render;
+mod store;
+#[cfg(test)]
+mod tests;
+
+use images::ImageStore;
+use render::QuoteRenderer;
+use store::{Deletion, DeletionRecord, NewQuote, Quote, QuoteStore};
+
+// 语录是谁、什么时候说的,直接发图收录时只知道被 @ 的人
+#[derive(Default)]
+struct Speaker {
+    id: Option<i64>,
+    name: String,
+    said_at: String,
+}
+
+struct HistoryBook {
+    store: QuoteStore,
+    images: ImageStore,
+    renderer: QuoteRenderer,
+    bot: Arc<RuntimeBot>,
+    // 协议端支持 ocr_image 时,收录时顺便识别图片里的文字
+    ocr_enabled: bool,
+    // 管理员导出的语录包放在这里
+    export_dir: PathBuf,
+}
+
+impl HistoryBook {
+    // @机器人 语录 [@说这句话的人] [说明] + 图片
+    async fn handle_save(&self, event: &MsgEvent, group_id: i64, quoted_id: Option<i64>, caption: String) {
+        let Some(image_ref) = event.message.get("image").iter().find_map(|segment| image_ref(&segment.data)) else {
+            return;
+        };
+        let speaker = Speaker {
+            id: quoted_id,
+            ..Speaker::default()
+        };
+        self.save_image(event, group_id, image_ref, speaker, caption).await;
+    }
+
+    // 回复一条消息并 @机器人 语录 [说明]: 有图收录图片,否则收录文字
+    async fn handle_save_reply(&self, event: &MsgEvent, group_id: i64, message_id: i32, caption: String) {
+        let data = match self.bot.get_msg(message_id).await {
+            Ok(result) => result.data,
+            Err(e) => {
+                eprintln!("获取被回复的消息 {} 失败: {:?}", message_id, e);
+                event.reply("找不到被回复的消息");
+                return;
+            }
+        };
+
+        let sender = &data["sender"];
+        let speaker = Speaker {
+            id: sender["user_id"].as_i64(),
+            name: sender["card"]
+                .as_str()
+                .filter(|card| !card.is_empty())
+                .or_else(|| sender["nickname"].as_str())
+                .unwrap_or_default()
+                .to_string(),
+            said_at: data["time"].as_i64().map(format_time).unwrap_or_default(),
+        };
+
+        let segments = data["message"].as_array().cloned().unwrap_or_default();
+        let image = segments
+            .iter()
+            .filter(|segment| segment["type"] == "image")
+            .find_map(|segment| image_ref(&segment["data"]));
+        if let Some(image) = image {
+            return self.save_image(event, group_id, image, speaker, caption).await;
+        }
+
+        let content = match data["message"].as_str() {
+            Some(raw) => strip_cq_codes(raw),
+            None => segments
+                .iter()
+                .filter(|segment| segment["type"] == "text")
+                .filter_map(|segment| segment["data"]["text"].as_str())
+                .collect(),
+        };
+        let content = content.trim().to_string();
+        if content.is_empty() {
+            event.reply("被回复的消息里没有文字或图片");
+            return;
+        }
+
+        let hash = format!("{:x}", Sha256::digest(format!("{}:{}", speaker.id.unwrap_or_default(), content)));
+        if self.is_duplicate(event, group_id, &hash).await {
+            return;
+        }
+        let quote = NewQuote {
+            group_id,
+            uploader_id: event.user_id,
+            uploader_name: sender_name(event),
+            quoted_id: speaker.id,
+            quoted_name: speaker.name,
+            created_at: now(),
+            said_at: speaker.said_at,
+            image_hash: hash,
+            image_file: String::new(),
+            content,
+            caption,
+            ocr_text: String::new(),
+            approved: true,
+        };
+        self.add_quote(event, quote).await;
+    }
+
+    async fn save_image(&self, event: &MsgEvent, group_id: i64, image_ref: ImageRef, speaker: Speaker, caption: String) {
+        let image = match self.images.download(&image_ref.url).await {
+            Ok(image) => image,
+            Err(e) => {
+                eprintln!("下载语录图片失败 {}: {:?}", image_ref.url, e);
+                event.reply("下载图片失败");
+                return;
+            }
+        };
+
+        if self.is_duplicate(event, group_id, &image.hash).await {
+            return;
+        }
+
+        let image_file = match self.images.save(&image).await {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("保存语录图片失败: {:?}", e);
+                event.reply("保存图片失败");
+                return;
+            }
+        };
+
+        let quote = NewQuote {
+            group_id,
+            uploader_id: event.user_id,
+            uploader_name: sender_name(event),
+            quoted_id: speaker.id,
+            quoted_name: speaker.name,
+            created_at: now(),
+            said_at: speaker.said_at,
+            image_hash: image.hash,
+            image_file,
+            content: String::new(),
+            caption,
+            ocr_text: self.recognize(&image_ref.file).await,
+            approved: true,
+        };
+        self.add_quote(event, quote).await;
+    }
+
+    // 已经收录过时顺便告诉编号;查询出错也当作重复,不再继续收录
+    async fn is_duplicate(&self, event: &MsgEvent, group_id: i64, hash: &str) -> bool {
+        match self.store.find_by_hash(group_id, hash).await {
+            Ok(Some(id)) => {
+                event.reply_and_quote(format!("已经收录过了, 编号 #{}", id));
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                eprintln!("查询语录失败: {:?}", e);
+                event.reply("读取语录本失败");
+                true
+            }
+        }
+    }
+
+    // 开启审核的群里,管理员以外的人收录的语录要等审核通过才能被抽到
+    async fn add_quote(&self, event: &MsgEvent, mut quote: NewQuote) {
+        if !self.is_group_manager(event) {
+            match self.store.requires_approval(quote.group_id).await {
+                Ok(required) => quote.approved = !required,
+                Err(e) => {
+                    eprintln!("读取语录审核设置失败: {:?}", e);
+                    event.reply("读取语录本失败");
+                    return;
+                }
+            }
+        }
+        let approved = quote.approved;
+        match self.store.add_quote(quote).await {
+            Ok(id) if approved => event.reply_and_quote(format!("已收录语录 #{}", id)),
+            Ok(id) => event.reply_and_quote(format!("已收录语录 #{}, 等管理员审核通过后才能被抽到", id)),
+            Err(e) => {
+                eprintln!("保存语录失败: {:?}", e);
+                event.reply("保存语录失败");
+            }
+        }
+    }
+
+    // @机器人 语录 删除 <编号>: 语录里说话的人和管理员可以删
+    async fn handle_delete(&self, event: &MsgEvent, group_id: i64, quote_id: Option<i64>) {
+        let Some(quote_id) = quote_id else {
+            event.reply("用法: @机器人 语录 删除 <编号>");
+            return;
+        };
+        let quote = match self.store.get_quote(group_id, quote_id).await {
+            Ok(Some(quote)) => quote,
+            Ok(None) => {
+                event.reply(format!("本群没有语录 #{}", quote_id));
+                return;
+            }
+            Err(e) => {
+                eprintln!("读取语录失败: {:?}", e);
+                event.reply("读取语录本失败");
+                return;
+            }
+        };
+        if !can_delete(&quote, event.user_id, self.is_group_manager(event)) {
+            event.reply("只有语录里说话的人和管理员可以删除语录");
+            return;
+        }
+
+        let deletion = Deletion {
+            deleted_by: event.user_id,
+            deleted_by_name: sender_name(event),
+            deleted_at: now(),
+        };
+        match self.store.delete_quote(group_id, quote_id, deletion).await {
+            Ok(true) => {
+                eprintln!("群 {} 的语录 #{} 被 {} 删除", group_id, quote_id, event.user_id);
+                // 删除待审核的语录就是拒绝收录
+                let action = if quote.approved { "删除" } else { "拒绝" };
+                event.reply_and_quote(format!("已{}语录 #{}", action, quote_id));
+            }
+            Ok(false) => event.reply(format!("本群没有语录 #{}", quote_id)),
+            Err(e) => {
+                eprintln!("删除语录失败: {:?}", e);
+                event.reply("删除语录失败");
+            }
+        }
+    }
+
+    // @机器人 语录 审核 开启/关闭,不带参数时查看当前设置
+    async fn handle_approval_mode(&self, event: &MsgEvent, group_id: i64, enabled: Option<bool>) {
+        let Some(enabled) = enabled else {
+            match self.store.requires_approval(group_id).await {
+                Ok(required) => event.reply(format!(
+                    "本群语录审核{}, 用法: @机器人 语录 审核 开启/关闭",
+                    if required { "已开启" } else { "未开启" }
+                )),
+                Err(e) => {
+                    eprintln!("读取语录审核设置失败: {:?}", e);
+                    event.reply("读取语录本失败");
+                }
+            }
+            return;
+        };
+        if !self.is_group_manager(event) {
+            event.reply("只有管理员可以修改语录审核设置");
+            return;
+        }
+
+        match self.store.set_requires_approval(group_id, enabled).await {
+            Ok(()) if enabled => event.reply("已开启语录审核, 管理员以外的人收录的语录需要审核通过"),
+            Ok(()) => event.reply("已关闭语录审核, 之前待审核的语录仍需逐条通过"),
+            Err(e) => {
+                eprintln!("保存语录审核设置失败: {:?}", e);
+                event.reply("保存设置失败");
+            }
+        }
+    }
+
+    // @机器人 语录 待审
+    async fn handle_pending(&self, event: &MsgEvent, group_id: i64) {
+        if !self.is_group_manager(event) {
+            event.reply("只有管理员可以查看待审核的语录");
+            return;
+        }
+        match self.store.pending_quotes(group_id, 10).await {
+            Ok(quotes) if quotes.is_empty() => event.reply("没有待审核的语录"),
+            Ok(quotes) => {
+                let mut lines = vec!["待审核的语录:".to_string()];
+                lines.extend(quotes.iter().map(summarize));
+                lines.push("@机器人 语录 通过 <编号> 通过审核, @机器人 语录 删除 <编号> 拒绝".to_string());
+                event.reply(lines.join("\n"));
+            }
+            Err(e) => {
+                eprintln!("读取待审核语录失败: {:?}", e);
+                event.reply("读取语录本失败");
+            }
+        }
+    }
+
+    // @机器人 语录 删除记录: 最近删掉的语录和删除人
+    async fn handle_deletion_log(&self, event: &MsgEvent, group_id: i64) {
+        if !self.is_group_manager(event) {
+            event.reply("只有管理员可以查看删除记录");
+            return;
+        }
+        match self.store.deletion_log(group_id, 10).await {
+            Ok(records) if records.is_empty() => event.reply("本群没有删除过语录"),
+            Ok(records) => {
+                let mut lines = vec!["最近删除的语录:".to_string()];
+                lines.extend(records.iter().map(summarize_deletion));
+                event.reply(lines.join("\n"));
+            }
+            Err(e) => {
+                eprintln!("读取删除记录失败: {:?}", e);
+                event.reply("读取语录本失败");
+            }
+        }
+    }
+
+    // @机器人 语录 通过 <编号>
+    async fn handle_approve(&self, event: &MsgEvent, group_id: i64, quote_id: Option<i64>) {
+        if !self.is_group_manager(event) {
+            event.reply("只有管理员可以审核语录");
+            return;
+        }
+        let Some(quote_id) = quote_id else {
+            event.reply("用法: @机器人 语录 通过 <编号>");
+            return;
+        };
+        match self.store.approve_quote(group_id, quote_id).await {
+            Ok(true) => event.reply_and_quote(format!("语录 #{} 已通过审核", quote_id)),
+            Ok(false) => event.reply(format!("本群没有待审核的语录 #{}", quote_id)),
+            Err(e) => {
+                eprintln!("审核语录失败: {:?}", e);
+                event.reply("保存语录失败");
+            }
+        }
+    }
+
+    // @机器人 语录 导出: 打包后上传到群文件
+    async fn handle_export(&self, event: &MsgEvent, group_id: i64) {
+        if !self.is_bot_admin(event) {
+            event.reply("只有机器人管理员可以导出语录本");
+            return;
+        }
+        let file_name = format!("语录-{}-{}.tar", group_id, Local::now().format("%Y%m%d-%H%M%S"));
+        let path = self.export_dir.join(&file_name);
+        let summary = match archive::export(&self.store, &self.images, group_id, &path, now()).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                eprintln!("导出语录本失败: {:?}", e);
+                event.reply("导出语录本失败");
+                return;
+            }
+        };
+
+        // 上传群文件要求协议端能读到这个路径,读不到时只告诉服务器上的位置
+        let path = std::fs::canonicalize(&path).unwrap_or(path);
+        let upload = self
+            .bot
+            .send_api_return(
+                "upload_group_file",
+                json!({ "group_id": group_id, "file": path.to_string_lossy(), "name": file_name }),
+            )
+            .await;
+        match upload {
+            Ok(_) => event.reply(format!("{}, 已上传到群文件", summary)),
+            Err(e) => {
+                eprintln!("上传语录包失败: {:?}", e);
+                event.reply(format!("{}, 上传群文件失败, 文件保存在 {}", summary, path.display()));
+            }
+        }
+    }
+
+    // @机器人 语录 导入 <服务器上的语录包路径>
+    async fn handle_import(&self, event: &MsgEvent, group_id: i64, path: &str) {
+        if !self.is_bot_admin(event) {
+            event.reply("只有机器人管理员可以导入语录本");
+            return;
+        }
+        if path.is_empty() {
+            event.reply("用法: @机器人 语录 导入 <语录包路径>");
+            return;
+        }
+        match archive::import(&self.store, &self.images, group_id, Path::new(path)).await {
+            Ok(summary) => event.reply(summary.to_string()),
+            Err(e) => {
+                eprintln!("导入语录本 {} 失败: {:?}", path, e);
+                event.reply(format!("导入语录本失败: {}", e));
+            }
+        }
+    }
+
+    // 群主、群管理员和 kovi.conf.toml 里的管理员
+    fn is_group_manager(&self, event: &MsgEvent) -> bool {
+        matches!(event.sender.role.as_deref(), Some("owner") | Some("admin")) || self.is_bot_admin(event)
+    }
+
+    // 导出导入会读写服务器上的文件,只给 kovi.conf.toml 里的管理员用
+    fn is_bot_admin(&self, event: &MsgEvent) -> bool {
+        self.bot
+            .get_all_admin()
+            .map(|admins| admins.contains(&event.user_id))
+            .unwrap_or(false)
+    }
+
+    // 识别失败不影响收录,只是不能按图里的文字查找
+    async fn recognize(&self, file: &str) -> String {
+        if !self.ocr_enabled || file.is_empty() {
+            return String::new();
+        }
+        match self.bot.send_api_return("ocr_image", json!({ "image": file })).await {
+            Ok(result) => ocr_text(&result.data),
+            Err(e) => {
+                eprintln!("识别语录图片文字失败: {:?}", e);
+                String::new()
+            }
+        }
+    }
+
+    // @机器人 语录 / @机器人 语录 @某人
+    async fn handle_random(&self, event: &MsgEvent, group_id: i64, quoted_id: Option<i64>) {
+        match self.store.random_quote(group_id, quoted_id).await {
+            Ok(Some(quote)) => self.reply_quote(event, &quote, None).await,
+            Ok(None) if quoted_id.is_some() => event.reply("还没有收录过 ta 的语录"),
+            Ok(None) => event.reply("本群还没有语录, 用法: @机器人 语录 [@说这句话的人] [说明] 并附上图片"),
+            Err(e) => {
+                eprintln!("读取语录失败: {:?}", e);
+                event.reply("读取语录本失败");
+            }
+        }
+    }
+
+    // @机器人 语录 搜索 <关键词>
+    async fn handle_search(&self, event: &MsgEvent, group_id: i64, keyword: &str) {
+        if keyword.is_empty() {
+            event.reply("用法: @机器人 语录 搜索 <关键词>");
+            return;
+        }
+        match self.store.search_quote(group_id, keyword).await {
+            Ok(Some((quote, total))) => {
+                let note = (total > 1).then(|| format!("共 {} 条匹配, 随机发一条", total));
+                self.reply_quote(event, &quote, note).await
+            }
+            Ok(None) => event.reply(format!("没有找到包含“{}”的语录", keyword)),
+            Err(e) => {
+                eprintln!("搜索语录失败: {:?}", e);
+                event.reply("读取语录本失败");
+            }
+        }
+    }
+
+    async fn reply_quote(&self, event: &MsgEvent, quote: &Quote, note: Option<String>) {
+        let mut lines = vec![describe(quote)];
+        lines.extend(note);
+        if !quote.content.is_empty() {
+            return self.reply_text_quote(event, quote, lines).await;
+        }
+
+        match self.images.load_base64(&quote.image_file).await {
+            Ok(image) => event.reply(KoviMessage::new().add_image(&image).add_text(lines.join("\n"))),
+            Err(e) => {
+                eprintln!("读取语录图片 {} 失败: {:?}", quote.image_file, e);
+                lines.push("(图片文件丢失)".to_string());
+                event.reply(lines.join("\n"));
+            }
+        }
+    }
+
+    // 文字语录画成卡片发出,没有字体或绘制失败时发纯文字
+    // 排版和 PNG 编码比较耗时,放到阻塞线程里做
+    async fn reply_text_quote(&self, event: &MsgEvent, quote: &Quote, lines: Vec<String>) {
+        let mut signature = format!("—— {}", speaker_label(quote).unwrap_or_else(|| "佚名".to_string()));
+        if !quote.said_at.is_empty() {
+            signature.push_str(&format!("  {}", quote.said_at));
+        }
+
+        let rendered = if self.renderer.has_font() {
+            let renderer = self.renderer.clone();
+            let (content, footer) = (quote.content.clone(), signature.clone());
+            match tokio::task::spawn_blocking(move || renderer.render(&content, &footer)).await {
+                Ok(Ok(png)) => Some(png),
+                Ok(Err(e)) => {
+                    eprintln!("绘制语录 #{} 失败: {:?}", quote.id, e);
+                    None
+                }
+                Err(e) => {
+                    eprintln!("绘制语录 #{} 失败: {:?}", quote.id, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        match rendered {
+            Some(png) => event.reply(
+                KoviMessage::new()
+                    .add_image(&format!("base64://{}", base64::encode(&png)))
+                    .add_text(lines.join("\n")),
+            ),
+            None => event.reply(format!("“{}”\n{}\n\n{}", quote.content, signature, lines.join("\n"))),
+        }
+    }
+}
+
+// 说话人的名字和 QQ 号,都不知道时为 None
+fn speaker_label(quote: &Quote) -> Option<String> {
+    match (quote.quoted_name.is_empty(), quote.quoted_id) {
+        (false, Some(id)) => Some(format!("{}({})", quote.quoted_name, id)),
+        (false, None) => Some(quote.quoted_name.clone()),
+        (true, Some(id)) => Some(id.to_string()),
+        (true, None) => None,
+    }
+}
+
+// 语录下面附带的信息: 编号、说话人、收录人和时间
+fn describe(quote: &Quote) -> String {
+    let mut text = format!("语录 #{}", quote.id);
+    if let Some(speaker) = speaker_label(quote) {
+        text.push_str(&format!(" · 出自 {}", speaker));
+    }
+    let uploader = if quote.uploader_name.is_empty() {
+        quote.uploader_id.to_string()
+    } else {
+        format!("{}({})", quote.uploader_name, quote.uploader_id)
+    };
+    text.push_str(&format!("\n{} 收录于 {}", uploader, quote.created_at));
+    if !quote.caption.is_empty() {
+        text.push_str(&format!("\n{}", quote.caption));
+    }
+    text
+}
+
+// 待审核列表里的一行: 编号、说话人和内容摘要
+fn summarize(quote: &Quote) -> String {
+    let mut line = format!("#{}", quote.id);
+    if let Some(speaker) = speaker_label(quote) {
+        line.push_str(&format!(" {}", speaker));
+    }
+    let preview = if quote.content.is_empty() {
+        format!("[图片] {}", quote.caption)
+    } else {
+        quote.content.chars().take(20).collect()
+    };
+    line.push_str(&format!(": {}", preview.trim()));
+    line
+}
+
+fn summarize_deletion(record: &DeletionRecord) -> String {
+    let mut line = format!("#{}", record.quote_id);
+    match (record.quoted_name.is_empty(), record.quoted_id) {
+        (false, _) => line.push_str(&format!(" {}", record.quoted_name)),
+        (true, Some(id)) => line.push_str(&format!(" {}", id)),
+        (true, None) => {}
+    }
+    let preview = if record.content.is_empty() {
+        format!("[图片] {}", record.caption)
+    } else {
+        record.content.chars().take(20).collect()
+    };
+    line.push_str(&format!(
+        ": {} ({} 被 {}({}) 删除)",
+        preview.trim(),
+        record.deleted_at,
+        record.deleted_by_name,
+        record.deleted_by
+    ));
+    line
+}
+
+// 语录里说话的人可以删自己的语录,管理员可以删任何一条
+fn can_delete(quote: &Quote, user_id: i64, is_manager: bool) -> bool {
+    is_manager || quote.quoted_id == Some(user_id)
+}
+
+fn now() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn format_time(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+// 字符串格式的消息里去掉 [CQ:...] 码,只留文字
+fn strip_cq_codes(raw: &str) -> String {
+    let mut text = String::new();
+    let mut rest = raw;
+    while let Some(start) = rest.find("[CQ:") {
+        text.push_str(&rest[..start]);
+        rest = match rest[start..].find(']') {
+            Some(end) => &rest[start + end + 1..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+    text.replace("&#91;", "[").replace("&#93;", "]").replace("&#44;", ",").replace("&amp;", "&")
+}
+
+// 群名片优先,没有时用昵称
+fn sender_name(event: &MsgEvent) -> String {
+    event
+        .sender
+        .card
+        .clone()
+        .filter(|card| !card.is_empty())
+        .or_else(|| event.sender.nickname.clone())
+        .unwrap_or_default()
+}
+
+// ocr_image 的返回: go-cqhttp 是 {texts: [{text}]},有的实现直接返回数组
+fn ocr_text(data: &Value) -> String {
+    let texts = data["texts"].as_array().or_else(|| data.as_array());
+    texts
+        .map(|texts| {
+            texts
+                .iter()
+                .filter_map(|item| item["text"].as_str())
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Route {
+    // 带图片时收录,quoted_id 是消息里除机器人以外被 @ 的人
+    Save {
+        group_id: i64,
+        quoted_id: Option<i64>,
+        caption: String,
+    },
+    // 回复某条消息时收录被回复的那条
+    SaveReply {
+        group_id: i64,
+        message_id: i32,
+        caption: String,
+    },
+    // 不带图片时随机发一条,@ 了人就只发他的
+    Random { group_id: i64, quoted_id: Option<i64> },
+    Search { group_id: i64, keyword: String },
+    // 编号写错时为 None,回复用法
+    Delete { group_id: i64, quote_id: Option<i64> },
+    Approve { group_id: i64, quote_id: Option<i64> },
+    // 不带开启/关闭时查看当前设置
+    ApprovalMode { group_id: i64, enabled: Option<bool> },
+    Pending { group_id: i64 },
+    DeletionLog { group_id: i64 },
+    Export { group_id: i64 },
+    // 语录包在服务器上的路径
+    Import { group_id: i64, path: String },
+}
+
+// 只处理群里 @机器人 并以“语录”开头的消息
+pub fn route(text: Option<&str>, message: &KoviMessage, self_id: i64, group_id: Option<i64>) -> Option<Route> {
+    let group_id = group_id?;
+    let command = text?.trim().strip_prefix("语录")?.trim();
+    let mentioned = mentions(message);
+    if !mentioned.contains(&self_id) {
+        return None;
+    }
+    let quoted_id = mentioned.into_iter().find(|id| *id != self_id);
+
+    if message.contains("image") {
+        return Some(Route::Save {
+            group_id,
+            quoted_id,
+            caption: command.to_string(),
+        });
+    }
+
+    // 管理命令优先,回复着语录发删除时不会被当成收录
+    let (action, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let rest = rest.trim();
+    match action {
+        "删除" => {
+            return Some(Route::Delete {
+                group_id,
+                quote_id: quote_id(rest),
+            });
+        }
+        "通过" => {
+            return Some(Route::Approve {
+                group_id,
+                quote_id: quote_id(rest),
+            });
+        }
+        "审核" => {
+            let enabled = match rest {
+                "开启" | "开" => Some(true),
+                "关闭" | "关" => Some(false),
+                _ => None,
+            };
+            return Some(Route::ApprovalMode { group_id, enabled });
+        }
+        "待审" => return Some(Route::Pending { group_id }),
+        "删除记录" => return Some(Route::DeletionLog { group_id }),
+        "导出" => return Some(Route::Export { group_id }),
+        "导入" => {
+            return Some(Route::Import {
+                group_id,
+                path: rest.to_string(),
+            });
+        }
+        _ => {}
+    }
+
+    if let Some(message_id) = reply_id(message) {
+        return Some(Route::SaveReply {
+            group_id,
+            message_id,
+            caption: command.to_string(),
+        });
+    }
+
+    match action {
+        "搜索" => Some(Route::Search {
+            group_id,
+            keyword: rest.to_string(),
+        }),
+        "" | "随机" => Some(Route::Random { group_id, quoted_id }),
+        _ => None,
+    }
+}
+
+// 语录编号,允许带 # 前缀
+fn quote_id(text: &str) -> Option<i64> {
+    text.trim_start_matches('#').parse().ok()
+}
+
+// 消息里被 @ 的 QQ 号,@全体成员 不算
+fn mentions(message: &KoviMessage) -> Vec<i64> {
+    message
+        .get("at")
+        .iter()
+        .filter_map(|segment| {
+            let qq = &segment.data["qq"];
+            qq.as_str().and_then(|qq| qq.parse().ok()).or_else(|| qq.as_i64())
+        })
+        .collect()
+}
+
+// 被回复的消息编号,有的实现给字符串有的给数字
+fn reply_id(message: &KoviMessage) -> Option<i32> {
+    message.get("reply").first().and_then(|segment| {
+        let id = &segment.data["id"];
+        id.as_str()
+            .and_then(|id| id.parse::<i32>().ok())
+            .or_else(|| id.as_i64().map(|id| id as i32))
+    })
+}
+
+// 图片消息段: url 用来下载,file 是协议端里的图片标识,识别文字时用
+struct ImageRef {
+    url: String,
+    file: String,
+}
+
+// 有的实现把下载地址放在 file 里
+fn image_ref(data: &Value) -> Option<ImageRef> {
+    let file = data["file"].as_str().unwrap_or_default();
+    let url = data["url"]
+        .as_str()
+        .or_else(|| Some(file).filter(|file| file.starts_with("http")))?;
+    Some(ImageRef {
+        url: url.to_string(),
+        file: file.to_string(),
+    })
+}
+
+// 语录数据库和图片目录,插件和命令行共用
+fn db_path() -> PathBuf {
+    env::var("HISTORY_BOOK_DB_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data/history-book/quotes.db"))
+}
+
+fn image_dir() -> PathBuf {
+    env::var("HISTORY_BOOK_IMAGE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data/history-book/images"))
+}
+
+// 命令行 export-quotes: 不启动机器人,直接把群的语录本打包到 path
+pub async fn export_quotes(group_id: i64, path: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let images = ImageStore::new(Client::new(), image_dir());
+    let summary = archive::export(&QuoteStore::new(db_path()), &images, group_id, path, now()).await?;
+    Ok(summary.to_string())
+}
+
+// 命令行 import-quotes: 把语录包导入到 group_id 这个群
+pub async fn import_quotes(group_id: i64, path: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let images = ImageStore::new(Client::new(), image_dir());
+    let summary = archive::import(&QuoteStore::new(db_path()), &images, group_id, path).await?;
+    Ok(summary.to_string())
+}
+
+#[kovi::plugin]
+async fn main() {
+    // 文字语录卡片用的字体,需要包含中文;仓库不带字体,见 assets/fonts/README.md。
+    // 设为 off 或字体不可用时文字语录发纯文字
+    let font_path = env::var("HISTORY_BOOK_FONT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("assets/fonts/quote.ttf"));
+    let renderer = if font_path == Path::new("off") {
+        QuoteRenderer::disabled()
+    } else {
+        QuoteRenderer::load(&font_path).unwrap_or_else(|e| {
+            eprintln!("语录字体不可用, 文字语录改发纯文字: {}。放入包含中文的字体即可生成卡片, 见 assets/fonts/README.md", e);
+            QuoteRenderer::disabled()
+        })
+    };
+    // 语录 导出 生成的语录包
+    let export_dir = env::var("HISTORY_BOOK_EXPORT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data/history-book/exports"));
+    // 协议端不支持 ocr_image 时可以设为 off
+    let ocr_enabled = env::var("HISTORY_BOOK_OCR").map(|value| value != "off").unwrap_or(true);
+
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(60))
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("创建 HTTP 客户端失败, 使用默认配置: {:?}", e);
+            Client::new()
+        });
+
+    let book = Arc::new(HistoryBook {
+        store: QuoteStore::new(db_path()),
+        images: ImageStore::new(client, image_dir()),
+        renderer,
+        bot: plugin::get_runtime_bot(),
+        ocr_enabled,
+        export_dir,
+    });
+
+    plugin::on_msg(move |event| {
+        let book = book.clone();
+        async move {
+            match route(event.borrow_text(), &event.message, event.self_id, event.group_id) {
+                Some(Route::Save {
+                    group_id,
+                    quoted_id,
+                    caption,
+                }) => book.handle_save(&event, group_id, quoted_id, caption).await,
+                Some(Route::SaveReply {
+                    group_id,
+                    message_id,
+                    caption,
+                }) => book.handle_save_reply(&event, group_id, message_id, caption).await,
+                Some(Route::Random { group_id, quoted_id }) => book.handle_random(&event, group_id, quoted_id).await,
+                Some(Route::Search { group_id, keyword }) => book.handle_search(&event, group_id, &keyword).await,
+                Some(Route::Delete { group_id, quote_id }) => book.handle_delete(&event, group_id, quote_id).await,
+                Some(Route::Approve { group_id, quote_id }) => book.handle_approve(&event, group_id, quote_id).await,
+                Some(Route::ApprovalMode { group_id, enabled }) => {
+                    book.handle_approval_mode(&event, group_id, enabled).await
+                }
+                Some(Route::Pending { group_id }) => book.handle_pending(&event, group_id).await,
+                Some(Route::DeletionLog { group_id }) => book.handle_deletion_log(&event, group_id).await,
+                Some(Route::Export { group_id }) => book.handle_export(&event, group_id).await,
+                Some(Route::Import { group_id, path }) => book.handle_import(&event, group_id, &path).await,
+                None => {}
+            }
+        }
+    });
+}
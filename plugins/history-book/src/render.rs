@@ -0,0 +1,109 @@
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use image::{ImageFormat, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut};
+use imageproc::rect::Rect;
+use std::error::Error;
+use std::io::Cursor;
+use std::path::Path;
+
+const WIDTH: u32 = 640;
+const PADDING: u32 = 40;
+const ACCENT_WIDTH: u32 = 6;
+const TEXT_SIZE: f32 = 30.0;
+const FOOTER_SIZE: f32 = 22.0;
+const LINE_GAP: f32 = 10.0;
+// 太长的语录只画前面这些行
+const MAX_LINES: usize = 24;
+const BACKGROUND: Rgba<u8> = Rgba([32, 34, 40, 255]);
+const ACCENT: Rgba<u8> = Rgba([230, 180, 80, 255]);
+const TEXT: Rgba<u8> = Rgba([240, 240, 240, 255]);
+const FOOTER: Rgba<u8> = Rgba([160, 164, 172, 255]);
+
+// 把文字语录画成引用卡片: 左边一条竖线,正文自动换行,右下角是说话人和时间
+#[derive(Clone)]
+pub struct QuoteRenderer {
+    font: Option<FontArc>,
+}
+
+impl QuoteRenderer {
+    // 字体必须能读、能解析并且包含中文,否则返回错误
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let bytes = std::fs::read(path).map_err(|e| format!("读取字体 {} 失败: {}", path.display(), e))?;
+        let font = FontArc::try_from_vec(bytes).map_err(|e| format!("字体 {} 无法解析: {}", path.display(), e))?;
+        if "语录".chars().any(|c| font.glyph_id(c).0 == 0) {
+            return Err(format!("字体 {} 不包含中文", path.display()).into());
+        }
+        Ok(Self { font: Some(font) })
+    }
+
+    // 明确关掉绘制时文字语录发纯文字
+    pub fn disabled() -> Self {
+        Self { font: None }
+    }
+
+    pub fn has_font(&self) -> bool {
+        self.font.is_some()
+    }
+
+    // 返回 PNG 编码后的图片
+    pub fn render(&self, content: &str, footer: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let font = self.font.as_ref().ok_or("没有可用的字体")?;
+        let text_width = (WIDTH - PADDING * 2 - ACCENT_WIDTH - PADDING / 2) as f32;
+        let mut lines = wrap(font, PxScale::from(TEXT_SIZE), content, text_width);
+        if lines.len() > MAX_LINES {
+            lines.truncate(MAX_LINES);
+            if let Some(last) = lines.last_mut() {
+                last.push('…');
+            }
+        }
+
+        let line_height = TEXT_SIZE + LINE_GAP;
+        let body_height = (lines.len() as f32 * line_height) as u32;
+        let height = PADDING * 2 + body_height + PADDING / 2 + FOOTER_SIZE as u32;
+        let mut canvas = RgbaImage::from_pixel(WIDTH, height, BACKGROUND);
+
+        draw_filled_rect_mut(&mut canvas, Rect::at(PADDING as i32, PADDING as i32).of_size(ACCENT_WIDTH, body_height), ACCENT);
+        let text_x = (PADDING + ACCENT_WIDTH + PADDING / 2) as i32;
+        for (i, line) in lines.iter().enumerate() {
+            let y = PADDING as f32 + i as f32 * line_height;
+            draw_text_mut(&mut canvas, TEXT, text_x, y as i32, PxScale::from(TEXT_SIZE), font, line);
+        }
+
+        // 署名靠右
+        let footer_scale = PxScale::from(FOOTER_SIZE);
+        let footer_width = line_width(font, footer_scale, footer);
+        let footer_x = (WIDTH - PADDING) as f32 - footer_width;
+        let footer_y = PADDING + body_height + PADDING / 2;
+        draw_text_mut(&mut canvas, FOOTER, footer_x.max(text_x as f32) as i32, footer_y as i32, footer_scale, font, footer);
+
+        let mut png = Vec::new();
+        canvas.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        Ok(png)
+    }
+}
+
+// 按字宽换行,中文没有空格,所以逐字计算
+fn wrap(font: &FontArc, scale: PxScale, text: &str, max_width: f32) -> Vec<String> {
+    let scaled = font.as_scaled(scale);
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut width = 0.0;
+        for c in paragraph.chars() {
+            let advance = scaled.h_advance(font.glyph_id(c));
+            if width + advance > max_width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                width = 0.0;
+            }
+            line.push(c);
+            width += advance;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn line_width(font: &FontArc, scale: PxScale, text: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    text.chars().map(|c| scaled.h_advance(font.glyph_id(c))).sum()
+}
@@ -0,0 +1,440 @@
+use kovi::tokio;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+// 一条新收录的语录,导出导入时也用它存完整的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewQuote {
+    pub group_id: i64,
+    pub uploader_id: i64,
+    pub uploader_name: String,
+    // 语录是谁说的,收录时没有 @ 就不知道
+    pub quoted_id: Option<i64>,
+    pub quoted_name: String,
+    pub created_at: String,
+    // 原消息的发送时间,回复收录时才有
+    pub said_at: String,
+    // 图片语录是图片内容的哈希,文字语录是说话人和正文的哈希
+    pub image_hash: String,
+    // 文字语录没有图片文件,正文存在 content 里
+    pub image_file: String,
+    pub content: String,
+    // 收录时写的说明和图片里识别出的文字,按关键词查找时用
+    pub caption: String,
+    pub ocr_text: String,
+    // 开启审核的群里普通成员收录的语录要等管理员通过
+    pub approved: bool,
+}
+
+// 谁在什么时候删的,写进删除记录
+#[derive(Debug, Clone)]
+pub struct Deletion {
+    pub deleted_by: i64,
+    pub deleted_by_name: String,
+    pub deleted_at: String,
+}
+
+// 删除记录里的一行,管理员查看用
+#[derive(Debug, Clone)]
+pub struct DeletionRecord {
+    pub quote_id: i64,
+    pub deleted_by: i64,
+    pub deleted_by_name: String,
+    pub deleted_at: String,
+    pub quoted_id: Option<i64>,
+    pub quoted_name: String,
+    pub content: String,
+    pub caption: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub id: i64,
+    pub uploader_id: i64,
+    pub uploader_name: String,
+    pub quoted_id: Option<i64>,
+    pub quoted_name: String,
+    pub created_at: String,
+    pub said_at: String,
+    pub image_file: String,
+    pub content: String,
+    pub caption: String,
+    pub approved: bool,
+}
+
+impl Quote {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            uploader_id: row.get(1)?,
+            uploader_name: row.get(2)?,
+            quoted_id: row.get(3)?,
+            quoted_name: row.get(4)?,
+            created_at: row.get(5)?,
+            said_at: row.get(6)?,
+            image_file: row.get(7)?,
+            content: row.get(8)?,
+            caption: row.get(9)?,
+            approved: row.get(10)?,
+        })
+    }
+}
+
+const QUOTE_COLUMNS: &str =
+    "id, uploader_id, uploader_name, quoted_id, quoted_name, created_at, said_at, image_file, content, caption, approved";
+
+// 语录本的 SQLite 存储,图片文件另外放在图片目录里
+#[derive(Clone)]
+pub struct QuoteStore {
+    path: PathBuf,
+}
+
+impl QuoteStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn open(&self) -> Result<Connection, Box<dyn Error + Send + Sync>> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS quotes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                group_id INTEGER NOT NULL,
+                uploader_id INTEGER NOT NULL,
+                uploader_name TEXT NOT NULL DEFAULT '',
+                quoted_id INTEGER,
+                quoted_name TEXT NOT NULL DEFAULT '',
+                created_at TEXT NOT NULL,
+                said_at TEXT NOT NULL DEFAULT '',
+                image_hash TEXT NOT NULL,
+                image_file TEXT NOT NULL,
+                content TEXT NOT NULL DEFAULT '',
+                caption TEXT NOT NULL DEFAULT '',
+                ocr_text TEXT NOT NULL DEFAULT '',
+                approved INTEGER NOT NULL DEFAULT 1,
+                UNIQUE (group_id, image_hash)
+            );
+            CREATE TABLE IF NOT EXISTS group_settings (
+                group_id INTEGER PRIMARY KEY,
+                require_approval INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS deletion_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                quote_id INTEGER NOT NULL,
+                group_id INTEGER NOT NULL,
+                deleted_by INTEGER NOT NULL,
+                deleted_by_name TEXT NOT NULL,
+                deleted_at TEXT NOT NULL,
+                uploader_id INTEGER NOT NULL,
+                quoted_id INTEGER,
+                quoted_name TEXT NOT NULL,
+                image_file TEXT NOT NULL,
+                content TEXT NOT NULL,
+                caption TEXT NOT NULL
+            );",
+        )?;
+        Ok(conn)
+    }
+
+    // 同一个群里同一张图只收录一次
+    pub async fn find_by_hash(&self, group_id: i64, image_hash: &str) -> Result<Option<i64>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let image_hash = image_hash.to_string();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let id = conn
+                .query_row(
+                    "SELECT id FROM quotes WHERE group_id = ?1 AND image_hash = ?2",
+                    params![group_id, image_hash],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(id)
+        })
+        .await?
+    }
+
+    // 保存语录,返回编号
+    pub async fn add_quote(&self, quote: NewQuote) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            conn.execute(
+                "INSERT INTO quotes (group_id, uploader_id, uploader_name, quoted_id, quoted_name, created_at, said_at,
+                                     image_hash, image_file, content, caption, ocr_text, approved)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    quote.group_id,
+                    quote.uploader_id,
+                    quote.uploader_name,
+                    quote.quoted_id,
+                    quote.quoted_name,
+                    quote.created_at,
+                    quote.said_at,
+                    quote.image_hash,
+                    quote.image_file,
+                    quote.content,
+                    quote.caption,
+                    quote.ocr_text,
+                    quote.approved
+                ],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await?
+    }
+
+    // 按编号取本群的语录,待审核的也算
+    pub async fn get_quote(&self, group_id: i64, id: i64) -> Result<Option<Quote>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let quote = conn
+                .query_row(
+                    &format!("SELECT {} FROM quotes WHERE group_id = ?1 AND id = ?2", QUOTE_COLUMNS),
+                    params![group_id, id],
+                    Quote::from_row,
+                )
+                .optional()?;
+            Ok(quote)
+        })
+        .await?
+    }
+
+    // 删除前把语录内容抄进删除记录,返回是否删掉了
+    pub async fn delete_quote(&self, group_id: i64, id: i64, deletion: Deletion) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = store.open()?;
+            let tx = conn.transaction()?;
+            let logged = tx.execute(
+                "INSERT INTO deletion_log (quote_id, group_id, deleted_by, deleted_by_name, deleted_at,
+                                           uploader_id, quoted_id, quoted_name, image_file, content, caption)
+                 SELECT id, group_id, ?3, ?4, ?5, uploader_id, quoted_id, quoted_name, image_file, content, caption
+                 FROM quotes WHERE group_id = ?1 AND id = ?2",
+                params![group_id, id, deletion.deleted_by, deletion.deleted_by_name, deletion.deleted_at],
+            )?;
+            tx.execute("DELETE FROM quotes WHERE group_id = ?1 AND id = ?2", params![group_id, id])?;
+            tx.commit()?;
+            Ok(logged > 0)
+        })
+        .await?
+    }
+
+    // 本群最近删除的语录,新的在前
+    pub async fn deletion_log(&self, group_id: i64, limit: u32) -> Result<Vec<DeletionRecord>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let mut stmt = conn.prepare(
+                "SELECT quote_id, deleted_by, deleted_by_name, deleted_at, quoted_id, quoted_name, content, caption
+                 FROM deletion_log WHERE group_id = ?1 ORDER BY id DESC LIMIT ?2",
+            )?;
+            let records = stmt
+                .query_map(params![group_id, limit], |row| {
+                    Ok(DeletionRecord {
+                        quote_id: row.get(0)?,
+                        deleted_by: row.get(1)?,
+                        deleted_by_name: row.get(2)?,
+                        deleted_at: row.get(3)?,
+                        quoted_id: row.get(4)?,
+                        quoted_name: row.get(5)?,
+                        content: row.get(6)?,
+                        caption: row.get(7)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<DeletionRecord>>>()?;
+            Ok(records)
+        })
+        .await?
+    }
+
+    // 通过审核,返回是否有待审核的这条
+    pub async fn approve_quote(&self, group_id: i64, id: i64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let updated = conn.execute(
+                "UPDATE quotes SET approved = 1 WHERE group_id = ?1 AND id = ?2 AND approved = 0",
+                params![group_id, id],
+            )?;
+            Ok(updated > 0)
+        })
+        .await?
+    }
+
+    // 最早的几条待审核语录
+    pub async fn pending_quotes(&self, group_id: i64, limit: u32) -> Result<Vec<Quote>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM quotes WHERE group_id = ?1 AND approved = 0 ORDER BY id LIMIT ?2",
+                QUOTE_COLUMNS
+            ))?;
+            let quotes = stmt
+                .query_map(params![group_id, limit], Quote::from_row)?
+                .collect::<rusqlite::Result<Vec<Quote>>>()?;
+            Ok(quotes)
+        })
+        .await?
+    }
+
+    pub async fn requires_approval(&self, group_id: i64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let required = conn
+                .query_row(
+                    "SELECT require_approval FROM group_settings WHERE group_id = ?1",
+                    params![group_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(required.unwrap_or(false))
+        })
+        .await?
+    }
+
+    pub async fn set_requires_approval(&self, group_id: i64, required: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            conn.execute(
+                "INSERT INTO group_settings (group_id, require_approval) VALUES (?1, ?2)
+                 ON CONFLICT(group_id) DO UPDATE SET require_approval = excluded.require_approval",
+                params![group_id, required],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    // 本群全部语录,按收录顺序,导出用
+    pub async fn export_quotes(&self, group_id: i64) -> Result<Vec<NewQuote>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let mut stmt = conn.prepare(
+                "SELECT group_id, uploader_id, uploader_name, quoted_id, quoted_name, created_at, said_at,
+                        image_hash, image_file, content, caption, ocr_text, approved
+                 FROM quotes WHERE group_id = ?1 ORDER BY id",
+            )?;
+            let quotes = stmt
+                .query_map(params![group_id], |row| {
+                    Ok(NewQuote {
+                        group_id: row.get(0)?,
+                        uploader_id: row.get(1)?,
+                        uploader_name: row.get(2)?,
+                        quoted_id: row.get(3)?,
+                        quoted_name: row.get(4)?,
+                        created_at: row.get(5)?,
+                        said_at: row.get(6)?,
+                        image_hash: row.get(7)?,
+                        image_file: row.get(8)?,
+                        content: row.get(9)?,
+                        caption: row.get(10)?,
+                        ocr_text: row.get(11)?,
+                        approved: row.get(12)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<NewQuote>>>()?;
+            Ok(quotes)
+        })
+        .await?
+    }
+
+    // 导入到指定的群,已经有的同一条跳过,返回新增的条数
+    pub async fn import_quotes(&self, group_id: i64, quotes: Vec<NewQuote>) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = store.open()?;
+            let tx = conn.transaction()?;
+            let mut added = 0;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT OR IGNORE INTO quotes (group_id, uploader_id, uploader_name, quoted_id, quoted_name, created_at,
+                                                   said_at, image_hash, image_file, content, caption, ocr_text, approved)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                )?;
+                for quote in quotes {
+                    added += stmt.execute(params![
+                        group_id,
+                        quote.uploader_id,
+                        quote.uploader_name,
+                        quote.quoted_id,
+                        quote.quoted_name,
+                        quote.created_at,
+                        quote.said_at,
+                        quote.image_hash,
+                        quote.image_file,
+                        quote.content,
+                        quote.caption,
+                        quote.ocr_text,
+                        quote.approved
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(added)
+        })
+        .await?
+    }
+
+    // 本群随机一条,指定了说话人时只在他的语录里选
+    pub async fn random_quote(&self, group_id: i64, quoted_id: Option<i64>) -> Result<Option<Quote>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let quote = conn
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM quotes WHERE group_id = ?1 AND approved = 1 AND (?2 IS NULL OR quoted_id = ?2)
+                         ORDER BY RANDOM() LIMIT 1",
+                        QUOTE_COLUMNS
+                    ),
+                    params![group_id, quoted_id],
+                    Quote::from_row,
+                )
+                .optional()?;
+            Ok(quote)
+        })
+        .await?
+    }
+
+    // 正文、说明或识别文字里包含关键词的语录,返回随机一条和匹配的总数
+    pub async fn search_quote(&self, group_id: i64, keyword: &str) -> Result<Option<(Quote, u32)>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let pattern = format!("%{}%", escape_like(keyword));
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let filter = "group_id = ?1 AND approved = 1
+                AND (content LIKE ?2 ESCAPE '\\' OR caption LIKE ?2 ESCAPE '\\' OR ocr_text LIKE ?2 ESCAPE '\\')";
+            let total: u32 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM quotes WHERE {}", filter),
+                params![group_id, pattern],
+                |row| row.get(0),
+            )?;
+            let quote = conn
+                .query_row(
+                    &format!("SELECT {} FROM quotes WHERE {} ORDER BY RANDOM() LIMIT 1", QUOTE_COLUMNS, filter),
+                    params![group_id, pattern],
+                    Quote::from_row,
+                )
+                .optional()?;
+            Ok(quote.map(|quote| (quote, total)))
+        })
+        .await?
+    }
+}
+
+// LIKE 里的 % 和 _ 按普通字符匹配
+fn escape_like(keyword: &str) -> String {
+    keyword.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
@@ -0,0 +1,298 @@
+// 语录本存储的测试,数据库放在临时目录
+use super::*;
+use tempfile::TempDir;
+
+const GROUP_ID: i64 = 30000;
+const OTHER_GROUP_ID: i64 = 30001;
+
+fn store() -> (QuoteStore, TempDir) {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    (QuoteStore::new(dir.path().join("quotes.db")), dir)
+}
+
+fn new_quote(group_id: i64, image_hash: &str) -> NewQuote {
+    NewQuote {
+        group_id,
+        uploader_id: 20000,
+        uploader_name: "收录的人".to_string(),
+        quoted_id: Some(20001),
+        quoted_name: "说话的人".to_string(),
+        created_at: "2024-05-01 12:00:00".to_string(),
+        said_at: String::new(),
+        image_hash: image_hash.to_string(),
+        image_file: format!("{}.png", image_hash),
+        content: String::new(),
+        caption: String::new(),
+        ocr_text: String::new(),
+        approved: true,
+    }
+}
+
+#[tokio::test]
+async fn same_image_is_saved_once_per_group() {
+    let (store, _dir) = store();
+    let id = store.add_quote(new_quote(GROUP_ID, "aaaa")).await.expect("add quote");
+    assert_eq!(store.find_by_hash(GROUP_ID, "aaaa").await.unwrap(), Some(id));
+    assert_eq!(store.find_by_hash(OTHER_GROUP_ID, "aaaa").await.unwrap(), None);
+
+    // UNIQUE(group_id, image_hash) 挡住重复收录,别的群可以收同一张图
+    assert!(store.add_quote(new_quote(GROUP_ID, "aaaa")).await.is_err());
+    let other = store.add_quote(new_quote(OTHER_GROUP_ID, "aaaa")).await.expect("add to other group");
+    assert_ne!(other, id);
+
+    // 导入时已经有的跳过
+    let added = store
+        .import_quotes(GROUP_ID, vec![new_quote(GROUP_ID, "aaaa"), new_quote(GROUP_ID, "bbbb")])
+        .await
+        .expect("import");
+    assert_eq!(added, 1);
+    assert_eq!(store.export_quotes(GROUP_ID).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn random_and_search_stay_in_the_group() {
+    let (store, _dir) = store();
+    let mut by_member = new_quote(GROUP_ID, "aaaa");
+    by_member.quoted_id = Some(20002);
+    by_member.caption = "周五晚上的火锅".to_string();
+    let by_member = store.add_quote(by_member).await.unwrap();
+    let mut text = new_quote(GROUP_ID, "bbbb");
+    text.content = "100% 会准时到".to_string();
+    let text = store.add_quote(text).await.unwrap();
+    let mut other_group = new_quote(OTHER_GROUP_ID, "cccc");
+    other_group.caption = "火锅".to_string();
+    store.add_quote(other_group).await.unwrap();
+
+    let quote = store.random_quote(GROUP_ID, Some(20002)).await.unwrap().expect("member quote");
+    assert_eq!(quote.id, by_member);
+    assert!(store.random_quote(GROUP_ID, Some(20003)).await.unwrap().is_none());
+    assert!(store.random_quote(GROUP_ID, None).await.unwrap().is_some());
+
+    let (quote, total) = store.search_quote(GROUP_ID, "火锅").await.unwrap().expect("caption match");
+    assert_eq!((quote.id, total), (by_member, 1));
+    // % 按普通字符匹配
+    let (quote, total) = store.search_quote(GROUP_ID, "100%").await.unwrap().expect("content match");
+    assert_eq!((quote.id, total), (text, 1));
+    assert!(store.search_quote(GROUP_ID, "10%到").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn unapproved_quotes_are_hidden_until_approved() {
+    let (store, _dir) = store();
+    let mut pending = new_quote(GROUP_ID, "aaaa");
+    pending.caption = "待审核".to_string();
+    pending.approved = false;
+    let id = store.add_quote(pending).await.unwrap();
+
+    assert!(store.random_quote(GROUP_ID, None).await.unwrap().is_none());
+    assert!(store.search_quote(GROUP_ID, "待审核").await.unwrap().is_none());
+    // 按编号还能取到,管理员审核时要用
+    assert!(!store.get_quote(GROUP_ID, id).await.unwrap().expect("pending quote").approved);
+    assert_eq!(store.pending_quotes(GROUP_ID, 10).await.unwrap().len(), 1);
+
+    assert!(store.approve_quote(GROUP_ID, id).await.unwrap());
+    assert!(!store.approve_quote(GROUP_ID, id).await.unwrap());
+    assert_eq!(store.random_quote(GROUP_ID, None).await.unwrap().map(|quote| quote.id), Some(id));
+    assert!(store.pending_quotes(GROUP_ID, 10).await.unwrap().is_empty());
+}
+
+#[test]
+fn only_the_quoted_member_or_a_manager_can_delete() {
+    let quote = Quote {
+        id: 1,
+        uploader_id: 20000,
+        uploader_name: String::new(),
+        quoted_id: Some(20001),
+        quoted_name: String::new(),
+        created_at: String::new(),
+        said_at: String::new(),
+        image_file: String::new(),
+        content: String::new(),
+        caption: String::new(),
+        approved: true,
+    };
+    assert!(can_delete(&quote, 20001, false));
+    assert!(can_delete(&quote, 40000, true));
+    // 收录的人不是说话的人,不能删
+    assert!(!can_delete(&quote, 20000, false));
+    assert!(!can_delete(&Quote { quoted_id: None, ..quote }, 20001, false));
+}
+
+#[tokio::test]
+async fn deleting_writes_the_deletion_log() {
+    let (store, _dir) = store();
+    let mut quote = new_quote(GROUP_ID, "aaaa");
+    quote.content = "明天一定早起".to_string();
+    let id = store.add_quote(quote).await.unwrap();
+    let other = store.add_quote(new_quote(OTHER_GROUP_ID, "bbbb")).await.unwrap();
+    let deletion = Deletion {
+        deleted_by: 40000,
+        deleted_by_name: "管理员".to_string(),
+        deleted_at: "2024-05-02 08:00:00".to_string(),
+    };
+
+    // 别的群的编号删不掉,也不留记录
+    assert!(!store.delete_quote(GROUP_ID, other, deletion.clone()).await.unwrap());
+    assert!(store.delete_quote(GROUP_ID, id, deletion).await.unwrap());
+    assert!(store.get_quote(GROUP_ID, id).await.unwrap().is_none());
+    assert!(store.get_quote(OTHER_GROUP_ID, other).await.unwrap().is_some());
+
+    let log = store.deletion_log(GROUP_ID, 10).await.unwrap();
+    assert_eq!(log.len(), 1);
+    let record = &log[0];
+    assert_eq!((record.quote_id, record.deleted_by), (id, 40000));
+    assert_eq!(record.deleted_by_name, "管理员");
+    assert_eq!(record.quoted_id, Some(20001));
+    assert_eq!(record.content, "明天一定早起");
+    assert!(store.deletion_log(OTHER_GROUP_ID, 10).await.unwrap().is_empty());
+    assert!(summarize_deletion(record).contains("被 管理员(40000) 删除"));
+}
+
+#[tokio::test]
+async fn oversized_image_download_is_refused() {
+    use api_mock::{MockApi, Reply};
+    let api = MockApi::start().await.expect("start mock api");
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let (_, images) = book(&dir);
+
+    // chunked 响应没有 Content-Length,只能边读边数
+    let body = "a".repeat(images::MAX_IMAGE_BYTES + 1);
+    api.enqueue("/huge.png", Reply::text(200, "image/png", &body).chunked());
+    let error = images.download(&api.url("/huge.png")).await.err().expect("oversized body");
+    assert_eq!(error.to_string(), "图片太大");
+
+    api.enqueue("/huge.png", Reply::text(200, "image/png", &body));
+    let error = images.download(&api.url("/huge.png")).await.err().expect("oversized body");
+    assert_eq!(error.to_string(), "图片太大");
+}
+
+#[test]
+fn unusable_font_is_an_error() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    assert!(QuoteRenderer::load(&dir.path().join("missing.ttf")).is_err());
+    let path = dir.path().join("broken.ttf");
+    std::fs::write(&path, b"not a font").unwrap();
+    assert!(QuoteRenderer::load(&path).is_err());
+    assert!(!QuoteRenderer::disabled().has_font());
+}
+
+// 导出导入用的临时语录本: 数据库和图片目录都在临时目录里
+fn book(dir: &TempDir) -> (QuoteStore, ImageStore) {
+    (
+        QuoteStore::new(dir.path().join("quotes.db")),
+        ImageStore::new(Client::new(), dir.path().join("images")),
+    )
+}
+
+fn png(seed: u8) -> (Vec<u8>, String) {
+    let bytes = [&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A][..], &[seed; 32]].concat();
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+    (bytes, hash)
+}
+
+// 手写一个语录包,entries 里的名字和类型原样写进 tar 头,用来模拟恶意的包
+fn write_archive(path: &Path, quotes: Vec<NewQuote>, entries: Vec<(&str, tar::EntryType, Vec<u8>)>) {
+    let mut builder = tar::Builder::new(std::fs::File::create(path).unwrap());
+    let manifest = json!({ "format": 1, "group_id": GROUP_ID, "exported_at": "", "quotes": quotes }).to_string();
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, "manifest.json", manifest.as_bytes()).unwrap();
+
+    for (name, kind, data) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(kind);
+        if kind == tar::EntryType::Symlink {
+            header.set_link_name("/etc/passwd").unwrap();
+        }
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, data.as_slice()).unwrap();
+    }
+    builder.into_inner().unwrap();
+}
+
+fn image_quote(hash: &str) -> NewQuote {
+    let mut quote = new_quote(GROUP_ID, hash);
+    quote.image_file = format!("{}.png", hash);
+    quote
+}
+
+#[tokio::test]
+async fn export_and_import_round_trip() {
+    let (source_dir, target_dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let (source, source_images) = book(&source_dir);
+    let (bytes, hash) = png(1);
+    let file = source_images
+        .save(&images::DownloadedImage {
+            bytes: bytes.clone(),
+            hash: hash.clone(),
+            extension: "png",
+        })
+        .await
+        .unwrap();
+    source.add_quote(image_quote(&hash)).await.unwrap();
+    let mut text = new_quote(GROUP_ID, "text-hash");
+    text.image_file = String::new();
+    text.content = "明天一定早起".to_string();
+    source.add_quote(text).await.unwrap();
+
+    let path = source_dir.path().join("语录.tar");
+    let exported = archive::export(&source, &source_images, GROUP_ID, &path, now()).await.unwrap();
+    assert_eq!(exported.to_string(), "导出 2 条语录, 1 张图片");
+
+    let (target, target_images) = book(&target_dir);
+    let imported = archive::import(&target, &target_images, OTHER_GROUP_ID, &path).await.unwrap();
+    assert_eq!(imported.to_string(), format!("从群 {} 的语录本导入 2 条, 新增 2 条", GROUP_ID));
+    assert_eq!(std::fs::read(target_images.path(&file)).unwrap(), bytes);
+
+    // 语录归到新的群,原来的群没有
+    let quotes = target.export_quotes(OTHER_GROUP_ID).await.unwrap();
+    assert_eq!(quotes.len(), 2);
+    assert!(quotes.iter().all(|quote| quote.group_id == OTHER_GROUP_ID));
+    assert!(target.export_quotes(GROUP_ID).await.unwrap().is_empty());
+
+    let again = archive::import(&target, &target_images, OTHER_GROUP_ID, &path).await.unwrap();
+    assert!(again.to_string().ends_with("新增 0 条, 2 条已经有了"), "{}", again);
+    assert_eq!(target.export_quotes(OTHER_GROUP_ID).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn import_rejects_escaping_and_forged_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let (store, images) = book(&dir);
+    let (bytes, hash) = png(2);
+    let (_, other_hash) = png(3);
+    let path = dir.path().join("bad.tar");
+    write_archive(
+        &path,
+        vec![image_quote(&hash), image_quote(&other_hash)],
+        vec![
+            ("images/../escape.png", tar::EntryType::Regular, bytes.clone()),
+            // 文件名是另一张图的哈希,内容对不上
+            (&format!("images/{}.png", other_hash), tar::EntryType::Regular, bytes.clone()),
+        ],
+    );
+
+    let summary = archive::import(&store, &images, GROUP_ID, &path).await.unwrap();
+    assert!(!dir.path().join("escape.png").exists());
+    assert!(!images.path(&format!("{}.png", other_hash)).exists());
+    assert!(summary.to_string().ends_with("新增 0 条, 2 条缺少图片被跳过"), "{}", summary);
+}
+
+#[tokio::test]
+async fn import_skips_symlink_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let (store, images) = book(&dir);
+    let (_, hash) = png(4);
+    let path = dir.path().join("link.tar");
+    let name = format!("images/{}.png", hash);
+    write_archive(&path, vec![image_quote(&hash)], vec![(&name, tar::EntryType::Symlink, vec![])]);
+
+    let summary = archive::import(&store, &images, GROUP_ID, &path).await.unwrap();
+    assert!(std::fs::symlink_metadata(images.path(&format!("{}.png", hash))).is_err());
+    assert!(summary.to_string().ends_with("1 条缺少图片被跳过"), "{}", summary);
+    assert!(store.export_quotes(GROUP_ID).await.unwrap().is_empty());
+}
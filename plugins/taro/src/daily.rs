@@ -0,0 +1,101 @@
+use crate::deck::{Arcana, Deck, DrawnCard};
+use crate::locale::{Locale, fill};
+use crate::store::DailyRank;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// 今日运势: 同一个人同一天固定抽到同一张牌,种子由 QQ 号和日期算出,重启后也不变
+pub fn daily_seed(user_id: i64, date: &str) -> u64 {
+    // FNV-1a,不用 DefaultHasher 是因为它的结果不保证跨版本稳定
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in format!("{}:{}", user_id, date).bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// 抽出当天的牌和幸运值(0-100)
+pub fn draw_daily(deck: &Deck, user_id: i64, date: &str) -> (DrawnCard, u32) {
+    let mut rng = StdRng::seed_from_u64(daily_seed(user_id, date));
+    let drawn = deck
+        .draw(1, &mut rng)
+        .into_iter()
+        .next()
+        .expect("deck is not empty");
+    let score = luck_score(&drawn, &mut rng);
+    (drawn, score)
+}
+
+// 正位的牌分数整体偏高,大阿卡纳的起伏更大
+fn luck_score(drawn: &DrawnCard, rng: &mut StdRng) -> u32 {
+    let spread = if drawn.card.arcana == Arcana::Major { 50 } else { 40 };
+    let roll = rng.random_range(0..=spread);
+    if drawn.upright {
+        100 - spread + roll
+    } else {
+        roll
+    }
+}
+
+pub fn daily_prompt(locale: &Locale, drawn: &DrawnCard, score: u32) -> String {
+    fill(
+        &locale.text.daily.prompt,
+        &[
+            ("card", &locale.describe(drawn)),
+            ("score", &score.to_string()),
+            ("label", locale.luck_label(score)),
+        ],
+    )
+}
+
+pub fn score_line(locale: &Locale, score: u32) -> String {
+    fill(
+        &locale.text.daily.score_line,
+        &[("score", &score.to_string()), ("label", locale.luck_label(score))],
+    )
+}
+
+// 离线时的今日运势
+pub fn offline_reading(locale: &Locale, drawn: &DrawnCard, score: u32) -> String {
+    let meaning = locale.meaning(drawn);
+    let reading = fill(
+        &locale.text.daily.offline,
+        &[
+            ("card", &locale.card(&drawn.card).name),
+            ("orientation", locale.orientation(drawn.upright)),
+            ("keywords", &locale.keywords(meaning)),
+            ("description", &meaning.description),
+        ],
+    );
+    format!("{}\n{}", reading, score_line(locale, score))
+}
+
+pub fn format_ranking(locale: &Locale, ranking: &[DailyRank], deck: &Deck) -> String {
+    if ranking.is_empty() {
+        return locale.text.daily.ranking_empty.clone();
+    }
+
+    let mut lines = vec![locale.text.daily.ranking_title.clone()];
+    for (i, rank) in ranking.iter().enumerate() {
+        let card_name = deck
+            .card(rank.card_index)
+            .map(|card| locale.card(card).name.as_str())
+            .unwrap_or("?");
+        let name = if rank.nickname.is_empty() {
+            rank.user_id.to_string()
+        } else {
+            rank.nickname.clone()
+        };
+        lines.push(format!(
+            "{}. {} - {} ({}) {} {}",
+            i + 1,
+            name,
+            rank.score,
+            locale.luck_label(rank.score),
+            card_name,
+            locale.orientation(rank.upright)
+        ));
+    }
+    lines.join("\n")
+}
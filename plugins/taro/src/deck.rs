@@ -0,0 +1,118 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+// 仓库自带的牌组数据,数据文件读取失败时兜底
+const BUNDLED_CARDS: &str = include_str!("../../../assets/tarot/cards.json");
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Arcana {
+    Major,
+    Minor,
+}
+
+// 牌在某个朝向下的关键词和释义
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CardMeaning {
+    pub keywords: Vec<String>,
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TaroCard {
+    pub index: u32,
+    pub name: String,
+    pub arcana: Arcana,
+    // wands / cups / swords / pentacles,大阿卡纳为空
+    pub suit: Option<String>,
+    pub upright: CardMeaning,
+    pub reversed: CardMeaning,
+}
+
+// 抽牌范围: 只用 22 张大阿卡纳,或者完整的 78 张
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeckMode {
+    MajorOnly,
+    Full,
+}
+
+impl DeckMode {
+    pub fn from_env_value(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "major" | "major_only" | "大阿卡纳" => DeckMode::MajorOnly,
+            _ => DeckMode::Full,
+        }
+    }
+}
+
+// 抽到的一张牌及其正逆位,牌面文字和正逆位的说法由语言文件决定
+#[derive(Debug, Clone)]
+pub struct DrawnCard {
+    pub card: TaroCard,
+    pub upright: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Deck {
+    pub cards: Vec<TaroCard>,
+}
+
+impl Deck {
+    pub fn load(path: &Path, mode: DeckMode) -> Self {
+        let cards = match read_cards(path) {
+            Ok(cards) => cards,
+            Err(e) => {
+                eprintln!("读取牌组文件 {:?} 失败, 使用内置牌组: {:?}", path, e);
+                serde_json::from_str(BUNDLED_CARDS).expect("bundled tarot deck is valid")
+            }
+        };
+
+        Self::from_cards(cards, mode)
+    }
+
+    pub fn from_cards(cards: Vec<TaroCard>, mode: DeckMode) -> Self {
+        let cards = match mode {
+            DeckMode::MajorOnly => cards.into_iter().filter(|card| card.arcana == Arcana::Major).collect(),
+            DeckMode::Full => cards,
+        };
+        Self { cards }
+    }
+
+    pub fn card(&self, index: u32) -> Option<&TaroCard> {
+        self.cards.iter().find(|card| card.index == index)
+    }
+
+    // 洗牌后从牌堆顶依次取牌,同一次占卜里不会出现重复的牌
+    pub fn draw<R: Rng + ?Sized>(&self, count: usize, rng: &mut R) -> Vec<DrawnCard> {
+        let mut pile = self.cards.clone();
+        pile.shuffle(rng);
+        pile.into_iter()
+            .take(count)
+            .map(|card| DrawnCard {
+                card,
+                upright: rng.random_bool(0.5),
+            })
+            .collect()
+    }
+}
+
+// 给了种子时每次启动抽牌顺序都一样,方便测试复现
+pub fn reading_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    }
+}
+
+fn read_cards(path: &Path) -> Result<Vec<TaroCard>, Box<dyn Error + Send + Sync>> {
+    let content = std::fs::read_to_string(path)?;
+    let cards: Vec<TaroCard> = serde_json::from_str(&content)?;
+    if cards.is_empty() {
+        return Err("牌组为空".into());
+    }
+    Ok(cards)
+}
@@ -0,0 +1,68 @@
+use crate::llm::Message;
+use crate::locale::Locale;
+use crate::store::StoredCard;
+use rand::rngs::StdRng;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::path::Path;
+
+// 一次起卦、抽符文或求签的结果,和塔罗一样存成牌阵名加 StoredCard 列表,
+// 历史记录、查看和追问都沿用塔罗那一套
+#[derive(Debug, Clone)]
+pub struct Casting {
+    pub spread: String,
+    pub items: Vec<StoredCard>,
+    pub question: String,
+}
+
+// 塔罗以外的占卜方式
+pub trait Oracle: Send + Sync {
+    // 命令里用的名字,比如 `运势 易经 问题`
+    fn names(&self) -> &'static [&'static str];
+    // 语言文件 divination 里对应的提示词
+    fn prompt_key(&self) -> &'static str;
+    // 历史记录里的牌阵名是否属于这种占卜
+    fn owns(&self, spread: &str) -> bool;
+    // 解析命令剩下的部分并起卦
+    fn cast(&self, input: &str, rng: &mut StdRng) -> Casting;
+    // 发给模型的结果描述,每条单独一行
+    fn describe(&self, locale: &Locale, casting: &Casting) -> Vec<String>;
+    fn interpret_offline(&self, locale: &Locale, casting: &Casting) -> String;
+}
+
+// 发给模型的对话: 这种占卜的系统提示、结果描述,最后是问题
+pub fn casting_messages(locale: &Locale, oracle: &dyn Oracle, casting: &Casting) -> Vec<Message> {
+    let text = locale.text.divination.get(oracle.prompt_key());
+    let system_prompt = text.map(|text| text.system_prompt.as_str()).unwrap_or(&locale.text.system_prompt);
+    let no_question = text.map(|text| text.no_question.as_str()).unwrap_or(&locale.text.no_question);
+
+    let mut messages: Vec<Message> = vec![Message::system(system_prompt)];
+    for line in oracle.describe(locale, casting) {
+        messages.push(Message::user(line));
+    }
+    messages.push(Message::user(if casting.question.is_empty() {
+        no_question
+    } else {
+        casting.question.as_str()
+    }));
+    messages
+}
+
+// 读取数据表,文件不存在或格式不对时用仓库自带的数据
+pub fn load_table<T: DeserializeOwned>(dir: &Path, file: &str, bundled: &str) -> Vec<T> {
+    match read_table(&dir.join(file)) {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("读取占卜数据 {} 失败, 使用内置数据: {:?}", file, e);
+            serde_json::from_str(bundled).expect("bundled divination table is valid")
+        }
+    }
+}
+
+fn read_table<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, Box<dyn Error + Send + Sync>> {
+    let table: Vec<T> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if table.is_empty() {
+        return Err("数据表为空".into());
+    }
+    Ok(table)
+}
@@ -0,0 +1,179 @@
+use crate::divination::{Casting, Oracle, load_table};
+use crate::locale::{Locale, fill};
+use crate::store::StoredCard;
+use rand::Rng;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use std::path::Path;
+
+const BUNDLED_HEXAGRAMS: &str = include_str!("../../../assets/divination/iching.json");
+const SPREAD_NAME: &str = "易经";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Hexagram {
+    pub number: u32,
+    pub name: String,
+    pub upper: String,
+    pub lower: String,
+    // 从初爻到上爻,1 为阳爻,0 为阴爻
+    pub lines: String,
+    pub meaning: String,
+}
+
+// 金钱卦: 三枚铜钱掷六次得到本卦,老阴老阳为变爻,变爻阴阳互换得到之卦
+// 记录里每个 StoredCard 的 index 是一爻的数值 (6-9),从初爻到上爻
+pub struct IChing {
+    hexagrams: Vec<Hexagram>,
+}
+
+impl IChing {
+    pub fn load(dir: &Path) -> Self {
+        Self {
+            hexagrams: load_table(dir, "iching.json", BUNDLED_HEXAGRAMS),
+        }
+    }
+
+    fn hexagram(&self, lines: &str) -> Option<&Hexagram> {
+        self.hexagrams.iter().find(|hexagram| hexagram.lines == lines)
+    }
+
+    fn describe_hexagram(&self, locale: &Locale, label: &str, lines: &str) -> String {
+        let text = &locale.text.iching;
+        match self.hexagram(lines) {
+            Some(hexagram) => fill(
+                &text.hexagram,
+                &[
+                    ("label", label),
+                    ("number", &hexagram.number.to_string()),
+                    ("name", &hexagram.name),
+                    ("upper", &hexagram.upper),
+                    ("lower", &hexagram.lower),
+                    ("meaning", &hexagram.meaning),
+                ],
+            ),
+            None => fill(&text.unknown_hexagram, &[("label", label), ("lines", lines)]),
+        }
+    }
+}
+
+// 字为 3、背为 2,三枚相加: 6 老阴、7 少阳、8 少阴、9 老阳
+fn toss_line(rng: &mut StdRng) -> u32 {
+    (0..3).map(|_| if rng.random_bool(0.5) { 3 } else { 2 }).sum()
+}
+
+fn is_yang(value: u32) -> bool {
+    value % 2 == 1
+}
+
+fn is_changing(value: u32) -> bool {
+    value == 6 || value == 9
+}
+
+fn primary_lines(values: &[u32]) -> String {
+    values.iter().map(|value| if is_yang(*value) { '1' } else { '0' }).collect()
+}
+
+fn changed_lines(values: &[u32]) -> String {
+    values
+        .iter()
+        .map(|value| if is_yang(*value) != is_changing(*value) { '1' } else { '0' })
+        .collect()
+}
+
+// 爻名: 初九、六二……上六
+fn line_name(position: usize, yang: bool) -> String {
+    let number = if yang { "九" } else { "六" };
+    match position {
+        0 => format!("初{}", number),
+        5 => format!("上{}", number),
+        _ => format!("{}{}", number, ["", "二", "三", "四", "五"][position]),
+    }
+}
+
+fn line_kind(locale: &Locale, value: u32) -> &str {
+    let kind = match value {
+        6 => 0,
+        7 => 1,
+        8 => 2,
+        _ => 3,
+    };
+    locale.text.iching.line_kinds.get(kind).map(String::as_str).unwrap_or_default()
+}
+
+// 变爻多少决定以哪一卦为主
+fn reading_focus(locale: &Locale, changing: usize) -> &str {
+    let focus = match changing {
+        0 => 0,
+        1 => 1,
+        2 | 3 => 2,
+        4 | 5 => 3,
+        _ => 4,
+    };
+    locale.text.iching.focus.get(focus).map(String::as_str).unwrap_or_default()
+}
+
+impl Oracle for IChing {
+    fn names(&self) -> &'static [&'static str] {
+        &["易经", "周易", "起卦", "卜卦"]
+    }
+
+    fn prompt_key(&self) -> &'static str {
+        "iching"
+    }
+
+    fn owns(&self, spread: &str) -> bool {
+        spread == SPREAD_NAME
+    }
+
+    fn cast(&self, input: &str, rng: &mut StdRng) -> Casting {
+        Casting {
+            spread: SPREAD_NAME.to_string(),
+            items: (0..6)
+                .map(|_| StoredCard {
+                    index: toss_line(rng),
+                    upright: true,
+                })
+                .collect(),
+            question: input.trim().to_string(),
+        }
+    }
+
+    fn describe(&self, locale: &Locale, casting: &Casting) -> Vec<String> {
+        let text = &locale.text.iching;
+        let values: Vec<u32> = casting.items.iter().map(|item| item.index).collect();
+        let changing: Vec<String> = values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| is_changing(**value))
+            .map(|(position, value)| line_name(position, is_yang(*value)))
+            .collect();
+
+        let separator = &locale.text.list_separator;
+        let mut lines = vec![self.describe_hexagram(locale, &text.primary, &primary_lines(&values))];
+        let six_lines = values
+            .iter()
+            .enumerate()
+            .map(|(position, value)| format!("{} {}", line_name(position, is_yang(*value)), line_kind(locale, *value)))
+            .collect::<Vec<String>>()
+            .join(separator);
+        lines.push(fill(&text.lines, &[("lines", &six_lines)]));
+        if changing.is_empty() {
+            lines.push(text.no_changing.clone());
+        } else {
+            lines.push(fill(&text.changing, &[("lines", &changing.join(separator))]));
+            lines.push(self.describe_hexagram(locale, &text.changed, &changed_lines(&values)));
+        }
+        lines.push(reading_focus(locale, changing.len()).to_string());
+        lines
+    }
+
+    fn interpret_offline(&self, locale: &Locale, casting: &Casting) -> String {
+        let mut lines = vec![locale.text.iching.offline_header.clone()];
+        if !casting.question.is_empty() {
+            lines.push(fill(&locale.text.offline.question, &[("question", &casting.question)]));
+        }
+        lines.push(String::new());
+        lines.extend(self.describe(locale, casting));
+        lines.join("\n")
+    }
+}
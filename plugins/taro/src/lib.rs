@@ -1,293 +1,737 @@
+use kovi::Message as KoviMessage;
+use kovi::MsgEvent;
 use kovi::PluginBuilder as plugin;
+use kovi::RuntimeBot;
+use kovi::chrono::Local;
 use kovi::tokio;
 use kovi::tokio::sync::Mutex; // 使用 tokio 的 Mutex
-use rand::Rng;
+use rand::rngs::StdRng;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::error::Error;
 use std::sync::Arc;
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct ChatCompletionResponse {
-    id: String,
-    object: String,
-    created: u64,
-    model: String,
-    choices: Vec<Choice>,
-    usage: Usage,
-    system_fingerprint: String,
+mod daily;
+mod deck;
+mod divination;
+mod iching;
+mod llm;
+mod locale;
+mod lots;
+mod offline;
+mod render;
+mod runes;
+mod session;
+mod spread;
+mod store;
+#[cfg(test)]
+mod tests;
+
+use deck::{Deck, DeckMode, DrawnCard, reading_rng};
+use divination::{Casting, Oracle, casting_messages};
+use iching::IChing;
+use llm::{ChatClient, Message};
+use locale::{Locale, Locales, fill};
+use lots::Lots;
+use render::SpreadRenderer;
+use runes::Runes;
+use session::SessionStore;
+use spread::{Spread, find_spread};
+use store::{DailyFortune, ReadingRecord, StoredCard, TaroStore};
+
+struct TaroService {
+    deck: Deck,
+    locales: Locales,
+    // 易经、符文、求签
+    oracles: Vec<Box<dyn Oracle>>,
+    rng: Mutex<StdRng>,
+    renderer: SpreadRenderer,
+    chat_client: ChatClient,
+    store: TaroStore,
+    sessions: SessionStore,
+    bot: Arc<RuntimeBot>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct Choice {
-    index: u32,
-    message: Message,
-    finish_reason: String,
+impl TaroService {
+    // 群里按设置的语言解读,私聊用默认语言
+    async fn locale(&self, event: &MsgEvent) -> &Locale {
+        let Some(group_id) = event.group_id else {
+            return self.locales.default_locale();
+        };
+        match self.store.group_language(group_id).await {
+            Ok(code) => self.locales.get(code.as_deref()),
+            Err(e) => {
+                eprintln!("Failed to load group language: {:?}", e);
+                self.locales.default_locale()
+            }
+        }
+    }
+
+    // 运势 [牌阵] [问题] / 运势 易经|符文|求签 [问题] / 运势 历史 / 运势 查看 <编号>
+    // 运势 追问 [编号] <问题> / 运势 语言 [代码]
+    async fn handle_reading(&self, event: &MsgEvent, command: &str) {
+        let (action, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        match action {
+            "牌阵" => {
+                let locale = self.locale(event).await;
+                event.reply(fill(&locale.text.replies.spreads_help, &[("spreads", &locale.spread_names())]));
+                return;
+            }
+            "历史" => return self.handle_history(event).await,
+            "查看" => return self.handle_view(event, rest.trim()).await,
+            "追问" => return self.handle_follow_up(event, rest.trim()).await,
+            "语言" => return self.handle_language(event, rest.trim()).await,
+            _ => {}
+        }
+        if let Some(oracle) = self.oracles.iter().find(|oracle| oracle.names().contains(&action)) {
+            return self.handle_casting(event, oracle.as_ref(), rest).await;
+        }
+
+        let locale = self.locale(event).await;
+        let (spread, question) = locale.parse_spread(command);
+
+        let drawn_cards = self.deck.draw(spread.card_count(), &mut *self.rng.lock().await);
+
+        // 先发牌阵图片,再等模型解读
+        self.reply_spread_image(event, spread, &drawn_cards).await;
+
+        let history_messages = reading_messages(locale, spread, &drawn_cards, &question);
+
+        // 模型不可用时退回离线解读,保证用户总能拿到结果
+        let reading = match self.chat_client.complete(&history_messages).await {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Tarot model unavailable, using offline reading: {:?}", e);
+                offline::interpret(locale, spread, &question, &drawn_cards)
+            }
+        };
+
+        let cards = drawn_cards
+            .iter()
+            .map(|drawn| StoredCard {
+                index: drawn.card.index,
+                upright: drawn.upright,
+            })
+            .collect();
+        self.finish_reading(event, locale, spread.name, cards, question, history_messages, reading)
+            .await;
+    }
+
+    // 易经、符文、求签: 没有牌阵图,其余流程和塔罗一样
+    async fn handle_casting(&self, event: &MsgEvent, oracle: &dyn Oracle, input: &str) {
+        let locale = self.locale(event).await;
+        let casting = oracle.cast(input, &mut *self.rng.lock().await);
+        let messages = casting_messages(locale, oracle, &casting);
+
+        let reading = match self.chat_client.complete(&messages).await {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Divination model unavailable, using offline reading: {:?}", e);
+                oracle.interpret_offline(locale, &casting)
+            }
+        };
+
+        self.finish_reading(
+            event,
+            locale,
+            &casting.spread,
+            casting.items,
+            casting.question,
+            messages,
+            reading,
+        )
+        .await;
+    }
+
+    // 保存会话和历史记录,再把解读发出去
+    #[allow(clippy::too_many_arguments)]
+    async fn finish_reading(
+        &self,
+        event: &MsgEvent,
+        locale: &Locale,
+        spread: &str,
+        cards: Vec<StoredCard>,
+        question: String,
+        mut messages: Vec<Message>,
+        reading: String,
+    ) {
+        messages.push(Message::assistant(reading.clone()));
+
+        let record = ReadingRecord {
+            id: 0,
+            user_id: event.user_id,
+            group_id: event.group_id,
+            created_at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            spread: spread.to_string(),
+            cards: cards.clone(),
+            question,
+            interpretation: reading.clone(),
+        };
+        let text = match self.store.add_reading(record).await {
+            Ok(id) => {
+                let footer = fill(&locale.text.reading_footer, &[("id", &id.to_string())]);
+                format!("{}\n\n{}", reading, footer)
+            }
+            Err(e) => {
+                eprintln!("Failed to save tarot reading: {:?}", e);
+                reading
+            }
+        };
+
+        // 留着这次的牌和对话,短时间内回复这条解读可以直接追问
+        let message_ids = self.reply_reading(event, text).await.into_iter().collect();
+        self.sessions
+            .put(event.group_id, event.user_id, spread.to_string(), cards, messages, message_ids);
+    }
+
+    // 引用提问的消息发出解读,返回机器人这条消息的编号
+    async fn reply_reading(&self, event: &MsgEvent, text: String) -> Option<i32> {
+        let mut params = json!({
+            "message": [
+                { "type": "reply", "data": { "id": event.message_id.to_string() } },
+                { "type": "text", "data": { "text": text } }
+            ]
+        });
+        match event.group_id {
+            Some(group_id) => {
+                params["message_type"] = json!("group");
+                params["group_id"] = json!(group_id);
+            }
+            None => {
+                params["message_type"] = json!("private");
+                params["user_id"] = json!(event.user_id);
+            }
+        }
+
+        match self.bot.send_api_return("send_msg", params).await {
+            Ok(result) => result.data["message_id"].as_i64().map(|id| id as i32),
+            Err(e) => {
+                eprintln!("Failed to send tarot reading: {:?}", e);
+                None
+            }
+        }
+    }
+
+    async fn handle_history(&self, event: &MsgEvent) {
+        let locale = self.locale(event).await;
+        let text = &locale.text.replies;
+        let readings = match self.store.recent_readings(event.user_id, 10).await {
+            Ok(readings) => readings,
+            Err(e) => {
+                eprintln!("Failed to load tarot history: {:?}", e);
+                event.reply(&text.record_failed);
+                return;
+            }
+        };
+        if readings.is_empty() {
+            event.reply(&text.history_empty);
+            return;
+        }
+
+        let mut lines = vec![text.history_title.clone()];
+        for record in &readings {
+            let question: String = record.question.chars().take(20).collect();
+            lines.push(fill(
+                &text.history_entry,
+                &[
+                    ("id", &record.id.to_string()),
+                    ("time", &record.created_at),
+                    ("spread", locale.record_spread_name(&record.spread)),
+                    ("question", if question.is_empty() { &text.history_no_question } else { &question }),
+                ],
+            ));
+        }
+        lines.push(text.history_hint.clone());
+        event.reply(lines.join("\n"));
+    }
+
+    async fn handle_view(&self, event: &MsgEvent, id: &str) {
+        let Some(record) = self.load_reading(event, id).await else {
+            return;
+        };
+        let locale = self.locale(event).await;
+        let text = &locale.text.replies;
+
+        let mut lines = vec![fill(
+            &text.record_header,
+            &[
+                ("id", &record.id.to_string()),
+                ("time", &record.created_at),
+                ("spread", locale.record_spread_name(&record.spread)),
+            ],
+        )];
+        if !record.question.is_empty() {
+            lines.push(fill(&text.record_question, &[("question", &record.question)]));
+        }
+
+        if let Some(oracle) = self.oracle_for(&record.spread) {
+            lines.extend(oracle.describe(locale, &casting_of(&record)));
+        } else {
+            let Some((spread, cards)) = self.restore_tarot(&record.spread, &record.cards) else {
+                event.reply(&text.cards_missing);
+                return;
+            };
+            self.reply_spread_image(event, spread, &cards).await;
+            for (position, drawn) in locale.positions(spread).iter().zip(&cards) {
+                lines.push(fill(
+                    &text.record_card,
+                    &[
+                        ("position", position),
+                        ("card", &locale.card(&drawn.card).name),
+                        ("orientation", locale.orientation(drawn.upright)),
+                    ],
+                ));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push(record.interpretation);
+        event.reply_and_quote(lines.join("\n"));
+    }
+
+    // 运势 追问 [编号] 问题: 带编号时从记录恢复,不带时接着最近一次占卜
+    async fn handle_follow_up(&self, event: &MsgEvent, command: &str) {
+        let (id, question) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        if id.parse::<i64>().is_err() {
+            return self.continue_session(event, command).await;
+        }
+
+        let locale = self.locale(event).await;
+        let question = question.trim();
+        if question.is_empty() {
+            event.reply(&locale.text.replies.usage_follow_up);
+            return;
+        }
+        let Some(record) = self.load_reading(event, id).await else {
+            return;
+        };
+
+        let mut messages = if let Some(oracle) = self.oracle_for(&record.spread) {
+            casting_messages(locale, oracle, &casting_of(&record))
+        } else {
+            let Some((spread, cards)) = self.restore_tarot(&record.spread, &record.cards) else {
+                event.reply(&locale.text.replies.cards_missing);
+                return;
+            };
+            reading_messages(locale, spread, &cards, &record.question)
+        };
+        messages.push(Message::assistant(record.interpretation));
+        self.ask_follow_up(event, record.spread, record.cards, messages, vec![], question)
+            .await;
+    }
+
+    // 回复进行中的占卜里机器人发的解读时当作追问,回复机器人的其他消息不算
+    async fn handle_reply(&self, event: &MsgEvent, question: &str) {
+        let Some(reply_id) = event.message.get("reply").first().and_then(|segment| {
+            let id = &segment.data["id"];
+            id.as_str()
+                .and_then(|id| id.parse::<i32>().ok())
+                .or_else(|| id.as_i64().map(|id| id as i32))
+        }) else {
+            return;
+        };
+        if question.is_empty() {
+            return;
+        }
+        match self.sessions.get(event.group_id, event.user_id) {
+            Some(session) if session.message_ids.contains(&reply_id) => {
+                self.continue_session(event, question).await
+            }
+            _ => {}
+        }
+    }
+
+    async fn continue_session(&self, event: &MsgEvent, question: &str) {
+        let text = &self.locale(event).await.text.replies;
+        let question = question.trim();
+        if question.is_empty() {
+            event.reply(&text.usage_follow_up);
+            return;
+        }
+        let Some(session) = self.sessions.get(event.group_id, event.user_id) else {
+            event.reply(&text.no_session);
+            return;
+        };
+        self.ask_follow_up(
+            event,
+            session.spread,
+            session.cards,
+            session.messages,
+            session.message_ids,
+            question,
+        )
+        .await;
+    }
+
+    // 带上原来的牌阵和解读继续问模型,问答会接在会话后面
+    async fn ask_follow_up(
+        &self,
+        event: &MsgEvent,
+        spread: String,
+        cards: Vec<StoredCard>,
+        mut messages: Vec<Message>,
+        mut message_ids: Vec<i32>,
+        question: &str,
+    ) {
+        let locale = self.locale(event).await;
+        messages.push(Message::user(fill(&locale.text.follow_up, &[("question", question)])));
+
+        let reading = match self.chat_client.complete(&messages).await {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Tarot model unavailable, using offline reading: {:?}", e);
+                self.offline_follow_up(locale, &spread, &cards, question)
+            }
+        };
+
+        messages.push(Message::assistant(reading.clone()));
+        message_ids.extend(self.reply_reading(event, reading).await);
+        self.sessions
+            .put(event.group_id, event.user_id, spread, cards, messages, message_ids);
+    }
+
+    // 追问时模型不可用,只能把原来的牌面解读再给一遍
+    fn offline_follow_up(&self, locale: &Locale, spread: &str, cards: &[StoredCard], question: &str) -> String {
+        if let Some(oracle) = self.oracle_for(spread) {
+            let casting = Casting {
+                spread: spread.to_string(),
+                items: cards.to_vec(),
+                question: question.to_string(),
+            };
+            return oracle.interpret_offline(locale, &casting);
+        }
+        match self.restore_tarot(spread, cards) {
+            Some((spread, cards)) => offline::interpret(locale, spread, question, &cards),
+            None => locale.text.replies.unavailable.clone(),
+        }
+    }
+
+    fn oracle_for(&self, spread: &str) -> Option<&dyn Oracle> {
+        self.oracles
+            .iter()
+            .find(|oracle| oracle.owns(spread))
+            .map(|oracle| oracle.as_ref())
+    }
+
+    // 按记录里的牌阵名和牌号还原塔罗牌阵
+    fn restore_tarot(&self, spread: &str, cards: &[StoredCard]) -> Option<(&'static Spread, Vec<DrawnCard>)> {
+        let spread = find_spread(spread)?;
+        let cards: Vec<DrawnCard> = cards
+            .iter()
+            .filter_map(|stored| {
+                self.deck.card(stored.index).map(|card| DrawnCard {
+                    card: card.clone(),
+                    upright: stored.upright,
+                })
+            })
+            .collect();
+        (cards.len() == spread.card_count()).then_some((spread, cards))
+    }
+
+    // 读取自己的记录,失败时直接回复原因
+    async fn load_reading(&self, event: &MsgEvent, id: &str) -> Option<ReadingRecord> {
+        let text = &self.locale(event).await.text.replies;
+        let Ok(id) = id.parse::<i64>() else {
+            event.reply(&text.record_bad_id);
+            return None;
+        };
+        match self.store.get_reading(event.user_id, id).await {
+            Ok(Some(record)) => Some(record),
+            Ok(None) => {
+                event.reply(fill(&text.record_not_found, &[("id", &id.to_string())]));
+                None
+            }
+            Err(e) => {
+                eprintln!("Failed to load tarot reading: {:?}", e);
+                event.reply(&text.record_failed);
+                None
+            }
+        }
+    }
+
+    // 运势 语言 [代码]: 查看或设置本群的解读语言,设置需要群管理员或机器人管理员
+    async fn handle_language(&self, event: &MsgEvent, code: &str) {
+        let current = self.locale(event).await;
+        let text = &current.text.replies;
+        let Some(group_id) = event.group_id else {
+            event.reply(&text.language_group_only);
+            return;
+        };
+        if code.is_empty() {
+            event.reply(fill(
+                &text.language_current,
+                &[("code", &current.text.code), ("names", &self.locales.names())],
+            ));
+            return;
+        }
+        if !self.is_group_manager(event) {
+            event.reply(&text.language_admin_only);
+            return;
+        }
+        let Some(locale) = self.locales.find(code) else {
+            event.reply(fill(&text.language_unknown, &[("names", &self.locales.names())]));
+            return;
+        };
+
+        // 设置成功的提示用新语言
+        match self.store.set_group_language(group_id, &locale.text.code).await {
+            Ok(()) => event.reply(fill(&locale.text.replies.language_set, &[("name", &locale.text.name)])),
+            Err(e) => {
+                eprintln!("Failed to save group language: {:?}", e);
+                event.reply(&text.language_save_failed);
+            }
+        }
+    }
+
+    fn is_group_manager(&self, event: &MsgEvent) -> bool {
+        matches!(event.sender.role.as_deref(), Some("owner") | Some("admin"))
+            || self
+                .bot
+                .get_all_admin()
+                .map(|admins| admins.contains(&event.user_id))
+                .unwrap_or(false)
+    }
+
+    // 今日运势 / 今日运势 排行
+    async fn handle_daily(&self, event: &MsgEvent, command: &str) {
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        let locale = self.locale(event).await;
+
+        if command == "排行" {
+            let Some(group_id) = event.group_id else {
+                event.reply(&locale.text.daily.ranking_group_only);
+                return;
+            };
+            match self.store.daily_ranking(group_id, &date, 10).await {
+                Ok(ranking) => event.reply(daily::format_ranking(locale, &ranking, &self.deck)),
+                Err(e) => {
+                    eprintln!("Failed to load daily ranking: {:?}", e);
+                    event.reply(&locale.text.daily.ranking_failed);
+                }
+            }
+            return;
+        }
+
+        let fortune = match self.daily_fortune(locale, event.user_id, &date).await {
+            Ok(fortune) => fortune,
+            Err(e) => {
+                eprintln!("Failed to load daily fortune: {:?}", e);
+                event.reply(&locale.text.daily.unavailable);
+                return;
+            }
+        };
+
+        if let Some(group_id) = event.group_id {
+            let nickname = event
+                .sender
+                .card
+                .clone()
+                .filter(|card| !card.is_empty())
+                .or_else(|| event.sender.nickname.clone())
+                .unwrap_or_default();
+            if let Err(e) = self.store.join_daily_rank(group_id, event.user_id, &date, &nickname).await {
+                eprintln!("Failed to record daily ranking: {:?}", e);
+            }
+        }
+
+        if let Some(card) = self.deck.card(fortune.card_index) {
+            let drawn = DrawnCard {
+                card: card.clone(),
+                upright: fortune.upright,
+            };
+            self.reply_spread_image(event, spread::daily_spread(), std::slice::from_ref(&drawn)).await;
+        }
+        event.reply_and_quote(&fortune.reading);
+    }
+
+    // 当天已经抽过就直接用缓存,否则按 QQ 号和日期抽牌并生成解读
+    async fn daily_fortune(
+        &self,
+        locale: &Locale,
+        user_id: i64,
+        date: &str,
+    ) -> Result<DailyFortune, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(fortune) = self.store.get_daily(user_id, date).await? {
+            return Ok(fortune);
+        }
+
+        let (drawn, score) = daily::draw_daily(&self.deck, user_id, date);
+        let messages = vec![
+            Message::system(&locale.text.system_prompt),
+            Message::user(daily::daily_prompt(locale, &drawn, score)),
+        ];
+        let reading = match self.chat_client.complete(&messages).await {
+            Ok(content) => format!("{}\n\n{}", content, daily::score_line(locale, score)),
+            Err(e) => {
+                eprintln!("Tarot model unavailable, using offline reading: {:?}", e);
+                daily::offline_reading(locale, &drawn, score)
+            }
+        };
+
+        let fortune = DailyFortune {
+            user_id,
+            date: date.to_string(),
+            card_index: drawn.card.index,
+            upright: drawn.upright,
+            score,
+            reading,
+        };
+        self.store.save_daily(fortune.clone()).await?;
+        // 并发请求时以先写入的那份为准
+        Ok(self.store.get_daily(user_id, date).await?.unwrap_or(fortune))
+    }
+
+    // 画图和编码 PNG 比较耗时,放到阻塞线程里做
+    async fn reply_spread_image(&self, event: &MsgEvent, spread: &'static Spread, cards: &[DrawnCard]) {
+        let renderer = self.renderer.clone();
+        let cards = cards.to_vec();
+        match tokio::task::spawn_blocking(move || renderer.render(spread, &cards)).await {
+            Ok(Ok(png)) => event.reply(
+                KoviMessage::new().add_image(&format!("base64://{}", base64::encode(&png))),
+            ),
+            Ok(Err(e)) => eprintln!("Failed to render spread: {:?}", e),
+            Err(e) => eprintln!("Failed to render spread: {:?}", e),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct Message {
-    role: String,
-    content: String,
+fn casting_of(record: &ReadingRecord) -> Casting {
+    Casting {
+        spread: record.spread.clone(),
+        items: record.cards.clone(),
+        question: record.question.clone(),
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct Usage {
-    prompt_tokens: u32,
-    completion_tokens: u32,
-    total_tokens: u32,
+// 发给模型的牌阵描述: 系统提示、牌阵、每个位置的牌,最后是问题
+fn reading_messages(locale: &Locale, spread: &Spread, cards: &[DrawnCard], question: &str) -> Vec<Message> {
+    let text = &locale.text;
+    let mut messages: Vec<Message> = vec![Message::system(&text.system_prompt)];
+
+    messages.push(Message::user(fill(
+        &text.spread_line,
+        &[
+            ("spread", locale.spread_name(spread)),
+            ("guidance", locale.spread_guidance(spread)),
+        ],
+    )));
+
+    for (position, drawn) in locale.positions(spread).iter().zip(cards) {
+        messages.push(Message::user(fill(
+            &text.position_line,
+            &[("position", position), ("card", &locale.describe(drawn))],
+        )));
+    }
+
+    messages.push(Message::user(if question.is_empty() {
+        text.no_question.as_str()
+    } else {
+        question
+    }));
+    messages
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-struct TaroCard {
-    index: u32,
-    name: &'static str,
-    description: &'static str,
+// 带着这个 @ 的运势消息不处理
+const SKIPPED_MENTION: &str = "[CQ:at,qq=3939271104]";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Route {
+    // 今日运势 [排行]
+    Daily(String),
+    // 运势 [子命令或牌阵] [问题]
+    Reading(String),
+    // 回复了某条消息,可能是在追问机器人的解读
+    Reply(String),
 }
 
-fn remove_prefix_if_starts_with(input: &str, prefix: &str) -> Option<String> {
-    // 判断 input 是否以 prefix 开头
-    if input.starts_with(prefix) {
-        // 删除 prefix 后的字符串
-        input.strip_prefix(prefix).map(|s| s.to_string())
+// 只看文本和消息段决定交给哪个处理函数,图片、表情等没有文本的消息直接忽略
+pub fn route(text: Option<&str>, raw_message: &str, message: &KoviMessage) -> Option<Route> {
+    let text = text?.trim();
+    if !text.starts_with("运势") && !text.starts_with("今日运势") {
+        return message.contains("reply").then(|| Route::Reply(text.to_string()));
+    }
+
+    if raw_message.contains(SKIPPED_MENTION) {
+        return None;
+    }
+
+    if let Some(command) = text.strip_prefix("今日运势") {
+        Some(Route::Daily(command.trim().to_string()))
     } else {
-        // 如果不以 prefix 开头，返回 None
-        None
+        text.strip_prefix("运势").map(|command| Route::Reading(command.trim().to_string()))
     }
 }
 
 #[kovi::plugin]
 async fn main() {
-    // 设置 API 密钥
+    // 设置 API 密钥,没有 key 时使用离线解读
     let api_key = env::var("SILICON_FLOW_API_KEY").unwrap_or_default();
-    println!("{}",api_key);
-    let api_url = "https://api.siliconflow.cn/v1/chat/completions";
-
-    let tarot_cards = vec![
-        TaroCard {
-            index: 0,
-            name: "愚者（The Fool）",
-            description: "新的开始、冒险、自由、未知",
-        },
-        TaroCard {
-            index: 1,
-            name: "魔术师（The Magician）",
-            description: "创造力、掌控、意志、潜力",
-        },
-        TaroCard {
-            index: 2,
-            name: "女祭司（The High Priestess）",
-            description: "直觉、神秘、智慧、潜意识",
-        },
-        TaroCard {
-            index: 3,
-            name: "皇后（The Empress）",
-            description: "繁荣、母性、创造、丰盛",
-        },
-        TaroCard {
-            index: 4,
-            name: "皇帝（The Emperor）",
-            description: "规则、权威、稳定、责任",
-        },
-        TaroCard {
-            index: 5,
-            name: "教皇（The Hierophant）",
-            description: "传统、信仰、指导、智慧",
-        },
-        TaroCard {
-            index: 6,
-            name: "恋人（The Lovers）",
-            description: "爱情、关系、选择、和谐",
-        },
-        TaroCard {
-            index: 7,
-            name: "战车（The Chariot）",
-            description: "意志力、胜利、掌控、自律",
-        },
-        TaroCard {
-            index: 8,
-            name: "力量（Strength）",
-            description: "内在力量、耐心、勇气、控制",
-        },
-        TaroCard {
-            index: 9,
-            name: "隐士（The Hermit）",
-            description: "内省、智慧、寻找真相、孤独",
-        },
-        TaroCard {
-            index: 10,
-            name: "命运之轮（Wheel of Fortune）",
-            description: "变化、命运、循环、机遇",
-        },
-        TaroCard {
-            index: 11,
-            name: "正义（Justice）",
-            description: "公正、平衡、因果、真相",
-        },
-        TaroCard {
-            index: 12,
-            name: "倒吊人（The Hanged Man）",
-            description: "牺牲、放下、顿悟、新视角",
-        },
-        TaroCard {
-            index: 13,
-            name: "死神（Death）",
-            description: "结束、新生、转变、蜕变",
-        },
-        TaroCard {
-            index: 14,
-            name: "节制（Temperance）",
-            description: "平衡、耐心、和谐、适度",
-        },
-        TaroCard {
-            index: 15,
-            name: "恶魔（The Devil）",
-            description: "诱惑、束缚、沉迷、物欲",
-        },
-        TaroCard {
-            index: 16,
-            name: "塔（The Tower）",
-            description: "突发变化、毁灭、觉醒、重建",
-        },
-        TaroCard {
-            index: 17,
-            name: "星星（The Star）",
-            description: "希望、灵性指引、启示、治愈",
-        },
-        TaroCard {
-            index: 18,
-            name: "月亮（The Moon）",
-            description: "潜意识、幻象、不安、直觉",
-        },
-        TaroCard {
-            index: 19,
-            name: "太阳（The Sun）",
-            description: "快乐、成功、积极、能量",
-        },
-        TaroCard {
-            index: 20,
-            name: "审判（Judgement）",
-            description: "觉醒、复苏、决定、救赎",
-        },
-        TaroCard {
-            index: 21,
-            name: "世界（The World）",
-            description: "完成、成就、整合、圆满",
-        },
-    ];
+    let api_url = env::var("SILICON_FLOW_API_URL")
+        .unwrap_or_else(|_| "https://api.siliconflow.cn/v1/chat/completions".to_string());
 
-    fn get_card(tarot_cards: &[TaroCard]) -> TaroCard {
-        // 获取随机数生成器
-        let mut rng = rand::thread_rng();
-        // 生成一个随机索引
-        let random_index = rng.gen_range(0..tarot_cards.len());
-        // 通过随机索引访问 Vec 中的元素
-        tarot_cards[random_index].clone()
-    }
+    // 牌组数据放在 assets/tarot/cards.json,TAROT_DECK=major 时只用大阿卡纳
+    let cards_path = env::var("TAROT_CARDS_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("assets/tarot/cards.json"));
+    let deck_mode = DeckMode::from_env_value(&env::var("TAROT_DECK").unwrap_or_default());
+    let deck = Deck::load(&cards_path, deck_mode);
+    // 提示词和牌面翻译放在 assets/tarot/locales,TAROT_LANG 是没有单独设置的群和私聊使用的语言
+    let locale_dir = env::var("TAROT_LOCALE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("assets/tarot/locales"));
+    let locales = Locales::load(&locale_dir, &env::var("TAROT_LANG").unwrap_or_else(|_| "zh-CN".to_string()));
+    // 易经、符文、求签的数据表
+    let divination_dir = env::var("DIVINATION_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("assets/divination"));
+    let oracles: Vec<Box<dyn Oracle>> = vec![
+        Box::new(IChing::load(&divination_dir)),
+        Box::new(Runes::load(&divination_dir)),
+        Box::new(Lots::load(&divination_dir)),
+    ];
+    // TAROT_SEED 设置后抽牌结果可复现
+    let seed = env::var("TAROT_SEED").ok().and_then(|seed| seed.parse::<u64>().ok());
+    let rng = Mutex::new(reading_rng(seed));
+    // 牌面图片目录,文件名为牌的 index
+    let art_dir = env::var("TAROT_ART_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("assets/tarot/cards"));
+    let renderer = SpreadRenderer::new(art_dir);
+    // 今日运势等数据存放的 SQLite 文件
+    let store_path = env::var("TAROT_DB_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data/taro/taro.db"));
+    // 占卜后可以直接追问的时间,默认 10 分钟
+    let session_ttl = env::var("TAROT_SESSION_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or(600);
 
     // 创建 HTTP 客户端
-    let client = Client::new();
+    let chat_client = ChatClient::new(Client::new(), api_url, api_key);
 
-    plugin::on_msg(move |event| {
-        let client_clone = client.clone();
-        let api_url_clone = api_url.to_string();
-        let api_key_clone = api_key.to_string();
-        let tarot_cards_clone = tarot_cards.clone();
-        println!(
-            "{:?}",
-            event
-                .borrow_text()
-                .unwrap()
-                .to_string()
-                .starts_with("运势 ")
-        );
+    let service = Arc::new(TaroService {
+        deck,
+        locales,
+        oracles,
+        rng,
+        renderer,
+        chat_client,
+        store: TaroStore::new(store_path),
+        sessions: SessionStore::new(Duration::from_secs(session_ttl)),
+        bot: plugin::get_runtime_bot(),
+    });
 
+    plugin::on_msg(move |event| {
+        let service = service.clone();
         async move {
-            if event.borrow_text().unwrap().to_string().starts_with("运势")
-                && !event.raw_message.contains("[CQ:at,qq=3939271104]")
-            {
-                // event.raw_message.contains("[CQ:at,qq=3939271104]") ||
-
-                let mut history_messages: Vec<Message> = vec![Message {
-                    content: "你是一个专业的塔罗牌占卜师,我会将客人抽到的三张牌的名字和正反位发给你,请你根据客人的问题帮他解答。
-                    注意关于解牌不能过于美化,不能曲解牌面本来的意义。
-                    解答需要简洁明了,不要犹豫不决。
-                    你的客户都是不懂塔罗牌的客户,只想知道关于他的问题的答案或者是他最近的运势情况,不要用神秘无意义的话术回答,解释一下牌的意义以及组合牌面回答问题即可。".to_string(),
-                    role: "system".to_string(),
-                }];
-
-                for i in 0..3 {
-                    let card = get_card(&tarot_cards_clone);
-                    let mut rng = rand::thread_rng();
-                    let is_upright = rng.gen_bool(0.5);
-                    let position = if is_upright { "正位" } else { "反位" };
-                    history_messages.push(Message {
-                        role: "user".to_string(),
-                        content: format!("牌名: {}, {}", card.name, position),
-                    });
-                }
-
-                let question =
-                    match remove_prefix_if_starts_with(event.borrow_text().unwrap(), "运势 ") {
-                        Some(question) => question,
-                        None => "用户没有问题,请按照牌面解答一下最近的运势以及可能会碰到的事"
-                            .to_string(),
-                    };
-
-                history_messages.push(Message {
-                    role: "user".to_string(),
-                    content: question,
-                });
-
-                let request_body = json!({
-                    "messages":history_messages,
-                    "model": "Pro/deepseek-ai/DeepSeek-R1",
-                        "frequency_penalty": 0,
-                        "max_tokens": 2048,
-                        "response_format": {
-                            "type": "text"
-                        },
-                        "stop": null,
-                        "stream": false,
-                        "stream_options": null,
-                        "temperature": 1.1,
-                        "top_p": 1,
-                        "n": 1,
-                });
-
-                println!("{}", request_body);
-
-                // 发送 POST 请求
-                let response = client_clone
-                    .post(api_url_clone)
-                    .header("Content-Type", "application/json")
-                    .header("Accept", "application/json")
-                    .header("Authorization", format!("Bearer {}", api_key_clone))
-                    .json(&request_body)
-                    .send()
-                    .await;
-
-                match response {
-                    Ok(response) => {
-                        if response.status().is_success() {
-                            let response_text = response
-                                .text()
-                                .await
-                                .unwrap_or_else(|_| "Failed to read response".to_string());
-                            println!("Response: {}", response_text);
-                            let response: ChatCompletionResponse =
-                                serde_json::from_str(&response_text).unwrap();
-                            event.reply_and_quote(&response.choices[0].message.content);
-                        } else {
-                            eprintln!("Failed to get a successful response: {}", response.status());
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to send request: {:?}", e);
-                    }
-                }
+            match route(event.borrow_text(), &event.raw_message, &event.message) {
+                Some(Route::Daily(command)) => service.handle_daily(&event, &command).await,
+                Some(Route::Reading(command)) => service.handle_reading(&event, &command).await,
+                Some(Route::Reply(question)) => service.handle_reply(&event, &question).await,
+                None => {}
             }
         }
     });
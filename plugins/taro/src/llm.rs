@@ -0,0 +1,119 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct ChatCompletionResponse {
+    id: String,
+    object: String,
+    created: u64,
+    model: String,
+    choices: Vec<Choice>,
+    usage: Usage,
+    system_fingerprint: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Choice {
+    index: u32,
+    message: Message,
+    finish_reason: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+impl Message {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Usage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    total_tokens: u32,
+}
+
+// 硅基流动的对话接口,没配置 key 或请求失败时返回错误,由调用方决定怎么兜底
+#[derive(Clone)]
+pub struct ChatClient {
+    client: Client,
+    api_url: String,
+    api_key: String,
+}
+
+impl ChatClient {
+    pub fn new(client: Client, api_url: String, api_key: String) -> Self {
+        Self { client, api_url, api_key }
+    }
+
+    pub async fn complete(&self, messages: &[Message]) -> Result<String, Box<dyn Error + Send + Sync>> {
+        if self.api_key.is_empty() {
+            return Err("SILICON_FLOW_API_KEY 未设置".into());
+        }
+
+        let request_body = json!({
+            "messages": messages,
+            "model": "Pro/deepseek-ai/DeepSeek-R1",
+            "frequency_penalty": 0,
+            "max_tokens": 2048,
+            "response_format": {
+                "type": "text"
+            },
+            "stop": null,
+            "stream": false,
+            "stream_options": null,
+            "temperature": 1.1,
+            "top_p": 1,
+            "n": 1,
+        });
+
+        // 发送 POST 请求
+        let response = self.client
+            .post(&self.api_url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&request_body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Failed to get a successful response: {}", response.status()).into());
+        }
+
+        let response_text = response.text().await?;
+        let response: ChatCompletionResponse = serde_json::from_str(&response_text)?;
+
+        response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .filter(|content| !content.trim().is_empty())
+            .ok_or_else(|| "模型没有返回内容".into())
+    }
+}
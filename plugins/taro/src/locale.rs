@@ -0,0 +1,420 @@
+use crate::deck::{CardMeaning, DrawnCard, TaroCard};
+use crate::spread::{SPREADS, Spread, find_spread, parse_spread};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+// 仓库自带的语言文件,语言目录里没有对应文件时兜底
+const BUNDLED_LOCALES: &[(&str, &str, Option<&str>)] = &[
+    ("zh-CN", include_str!("../../../assets/tarot/locales/zh-CN.json"), None),
+    (
+        "en",
+        include_str!("../../../assets/tarot/locales/en.json"),
+        Some(include_str!("../../../assets/tarot/locales/en.cards.json")),
+    ),
+];
+// 牌阵的位置名和解读要求以这种语言为准
+const BASE_LOCALE: &str = "zh-CN";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DailyText {
+    pub prompt: String,
+    pub score_line: String,
+    pub offline: String,
+    // 从高到低: 90+、70+、50+、30+、10+、其余
+    pub labels: Vec<String>,
+    pub ranking_title: String,
+    pub ranking_empty: String,
+    pub ranking_group_only: String,
+    pub ranking_failed: String,
+    pub unavailable: String,
+}
+
+// 命令的回复: 用法、历史记录、出错提示等
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReplyText {
+    pub spreads_help: String,
+    pub usage_follow_up: String,
+    pub no_session: String,
+    pub unavailable: String,
+    pub cards_missing: String,
+    pub history_title: String,
+    pub history_entry: String,
+    pub history_no_question: String,
+    pub history_hint: String,
+    pub history_empty: String,
+    pub record_header: String,
+    pub record_question: String,
+    pub record_card: String,
+    pub record_bad_id: String,
+    pub record_not_found: String,
+    pub record_failed: String,
+    pub language_group_only: String,
+    pub language_current: String,
+    pub language_admin_only: String,
+    pub language_unknown: String,
+    pub language_set: String,
+    pub language_save_failed: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct OfflineText {
+    pub header: String,
+    pub question: String,
+    pub entry: String,
+    pub answer_yes: String,
+    pub answer_no: String,
+    pub mostly_upright: String,
+    pub mostly_reversed: String,
+    pub balanced: String,
+    pub many_majors: String,
+    pub suit_focus: String,
+    pub last_card: String,
+    pub themes: HashMap<String, String>,
+}
+
+// 牌阵的名字、位置名和给模型的解读要求;aliases 是这种语言下选牌阵时能用的叫法。
+// 易经、求签只需要 name,用来显示历史记录。缺少的位置名和解读要求用自带的 zh-CN 补上
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpreadText {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub positions: Vec<String>,
+    #[serde(default)]
+    pub guidance: String,
+}
+
+// 易经的卦象描述,爻名 (初九、六二……) 和卦名沿用中文
+#[derive(Debug, Deserialize, Clone)]
+pub struct IChingText {
+    pub offline_header: String,
+    pub primary: String,
+    pub changed: String,
+    pub hexagram: String,
+    pub unknown_hexagram: String,
+    pub lines: String,
+    pub no_changing: String,
+    pub changing: String,
+    // 老阴、少阳、少阴、老阳
+    pub line_kinds: Vec<String>,
+    // 变爻个数: 0、1、2-3、4-5、6
+    pub focus: Vec<String>,
+}
+
+// 符文的描述,符文名和释义沿用符文表里的文字
+#[derive(Debug, Deserialize, Clone)]
+pub struct RunesText {
+    pub offline_header: String,
+    pub spread_line: String,
+    pub rune_line: String,
+    pub no_reversed: String,
+}
+
+// 签文的描述,签题、签诗和解签沿用签表里的文字
+#[derive(Debug, Deserialize, Clone)]
+pub struct LotsText {
+    pub offline_header: String,
+    pub question: String,
+    pub lot: String,
+    pub poem: String,
+    pub meaning: String,
+}
+
+// 易经、符文、求签各自的提示词
+#[derive(Debug, Deserialize, Clone)]
+pub struct DivinationText {
+    pub system_prompt: String,
+    pub no_question: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LocaleText {
+    pub code: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    // 牌面翻译文件,和语言文件放在同一目录;为空时使用牌组本身的文字
+    pub cards: Option<String>,
+    pub system_prompt: String,
+    pub no_question: String,
+    pub spread_line: String,
+    pub position_line: String,
+    pub card_line: String,
+    pub follow_up: String,
+    pub upright: String,
+    pub reversed: String,
+    pub list_separator: String,
+    pub sentence_separator: String,
+    pub reading_footer: String,
+    pub daily: DailyText,
+    pub replies: ReplyText,
+    pub offline: OfflineText,
+    pub iching: IChingText,
+    pub runes: RunesText,
+    pub lots: LotsText,
+    pub spreads: HashMap<String, SpreadText>,
+    // 键是占卜方式的 prompt_key,没有时沿用塔罗的提示词
+    #[serde(default)]
+    pub divination: HashMap<String, DivinationText>,
+}
+
+// 一种语言: 提示词、输出文字和按 index 对应的牌面翻译
+#[derive(Debug, Clone)]
+pub struct Locale {
+    pub text: LocaleText,
+    cards: HashMap<u32, TaroCard>,
+}
+
+impl Locale {
+    fn matches(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.text.code.to_lowercase() == word || self.text.aliases.iter().any(|alias| alias.to_lowercase() == word)
+    }
+
+    pub fn card<'a>(&'a self, card: &'a TaroCard) -> &'a TaroCard {
+        self.cards.get(&card.index).unwrap_or(card)
+    }
+
+    pub fn meaning<'a>(&'a self, drawn: &'a DrawnCard) -> &'a CardMeaning {
+        let card = self.card(&drawn.card);
+        if drawn.upright { &card.upright } else { &card.reversed }
+    }
+
+    pub fn orientation(&self, upright: bool) -> &str {
+        if upright { &self.text.upright } else { &self.text.reversed }
+    }
+
+    pub fn keywords(&self, meaning: &CardMeaning) -> String {
+        meaning.keywords.join(&self.text.list_separator)
+    }
+
+    // 发给模型的牌面描述,带上当前朝向的关键词和释义
+    pub fn describe(&self, drawn: &DrawnCard) -> String {
+        let meaning = self.meaning(drawn);
+        fill(
+            &self.text.card_line,
+            &[
+                ("name", &self.card(&drawn.card).name),
+                ("orientation", self.orientation(drawn.upright)),
+                ("keywords", &self.keywords(meaning)),
+                ("description", &meaning.description),
+            ],
+        )
+    }
+
+    pub fn spread_name<'a>(&'a self, spread: &'a Spread) -> &'a str {
+        self.text.spreads.get(spread.name).map(|text| text.name.as_str()).unwrap_or(spread.name)
+    }
+
+    // 可用的塔罗牌阵,用这种语言的名字列出
+    pub fn spread_names(&self) -> String {
+        SPREADS
+            .iter()
+            .map(|spread| self.spread_name(spread))
+            .collect::<Vec<&str>>()
+            .join(&self.text.list_separator)
+    }
+
+    // 先按这种语言的牌阵名和别名解析(可以有空格,比如 Celtic Cross),找不到再按中文牌阵名
+    pub fn parse_spread(&self, input: &str) -> (&'static Spread, String) {
+        let input = input.trim();
+        let lowered = input.to_lowercase();
+        let matched = self
+            .text
+            .spreads
+            .iter()
+            .filter_map(|(key, text)| find_spread(key).map(|spread| (spread, text)))
+            .flat_map(|(spread, text)| std::iter::once(&text.name).chain(&text.aliases).map(move |alias| (spread, alias)))
+            .filter(|(_, alias)| {
+                let alias = alias.to_lowercase();
+                !alias.is_empty()
+                    && lowered.starts_with(&alias)
+                    && lowered[alias.len()..].chars().next().is_none_or(char::is_whitespace)
+            })
+            .max_by_key(|(_, alias)| alias.to_lowercase().len());
+
+        match matched {
+            Some((spread, alias)) => (spread, lowered_rest(input, alias.to_lowercase().len())),
+            None => parse_spread(input),
+        }
+    }
+
+    // 记录里保存的牌阵名 (塔罗牌阵、易经、符文牌阵或求签) 的译名
+    pub fn record_spread_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.text.spreads.get(name).map(|text| text.name.as_str()).unwrap_or(name)
+    }
+
+    pub fn spread_guidance(&self, spread: &Spread) -> &str {
+        self.text.spreads.get(spread.name).map(|text| text.guidance.as_str()).unwrap_or_default()
+    }
+
+    pub fn positions(&self, spread: &Spread) -> Vec<&str> {
+        self.text
+            .spreads
+            .get(spread.name)
+            .map(|text| text.positions.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn luck_label(&self, score: u32) -> &str {
+        let level = match score {
+            90.. => 0,
+            70..=89 => 1,
+            50..=69 => 2,
+            30..=49 => 3,
+            10..=29 => 4,
+            _ => 5,
+        };
+        self.text.daily.labels.get(level).map(String::as_str).unwrap_or_default()
+    }
+}
+
+// 所有可用的语言,找不到时用默认语言
+pub struct Locales {
+    locales: Vec<Locale>,
+    default_index: usize,
+}
+
+impl Locales {
+    // 语言目录里的 <code>.json 覆盖自带的同名语言,也可以新增语言
+    pub fn load(dir: &Path, default_code: &str) -> Self {
+        let base_spreads = BUNDLED_LOCALES
+            .iter()
+            .find(|(code, _, _)| *code == BASE_LOCALE)
+            .map(|(_, text, _)| serde_json::from_str::<LocaleText>(text).expect("bundled tarot locale is valid").spreads)
+            .unwrap_or_default();
+
+        let mut locales: Vec<Locale> = BUNDLED_LOCALES
+            .iter()
+            .map(|(code, text, cards)| {
+                let text: LocaleText = serde_json::from_str(text).expect("bundled tarot locale is valid");
+                let cards = cards
+                    .map(|cards| serde_json::from_str(cards).expect("bundled tarot locale cards are valid"))
+                    .unwrap_or_default();
+                let (text, cards) = match read_locale(dir, code) {
+                    Ok(Some(locale)) => locale,
+                    Ok(None) => (text, cards),
+                    Err(e) => {
+                        eprintln!("读取语言文件 {} 失败, 使用内置版本: {:?}", code, e);
+                        (text, cards)
+                    }
+                };
+                Locale {
+                    text: complete_spreads(text, &base_spreads),
+                    cards: index_cards(cards),
+                }
+            })
+            .collect();
+
+        for code in extra_locale_codes(dir) {
+            match read_locale(dir, &code) {
+                Ok(Some((text, cards))) => locales.push(Locale {
+                    text: complete_spreads(text, &base_spreads),
+                    cards: index_cards(cards),
+                }),
+                Ok(None) => {}
+                Err(e) => eprintln!("读取语言文件 {} 失败: {:?}", code, e),
+            }
+        }
+
+        let default_index = locales
+            .iter()
+            .position(|locale| locale.matches(default_code))
+            .unwrap_or(0);
+        Self { locales, default_index }
+    }
+
+    pub fn default_locale(&self) -> &Locale {
+        &self.locales[self.default_index]
+    }
+
+    // 按代码或别名查找,比如 en / english / 英文
+    pub fn find(&self, word: &str) -> Option<&Locale> {
+        self.locales.iter().find(|locale| locale.matches(word.trim()))
+    }
+
+    pub fn get(&self, code: Option<&str>) -> &Locale {
+        code.and_then(|code| self.find(code)).unwrap_or_else(|| self.default_locale())
+    }
+
+    pub fn names(&self) -> String {
+        self.locales
+            .iter()
+            .map(|locale| format!("{}({})", locale.text.code, locale.text.name))
+            .collect::<Vec<String>>()
+            .join("、")
+    }
+}
+
+// 把模板里的 {key} 换成对应的值
+pub fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| text.replace(&format!("{{{}}}", key), value))
+}
+
+// 别名按小写匹配,小写后长度可能变化,所以按字符数截掉原文里的牌阵名
+fn lowered_rest(input: &str, lowered_len: usize) -> String {
+    let mut consumed = 0;
+    let rest: String = input
+        .chars()
+        .skip_while(|c| {
+            let skip = consumed < lowered_len;
+            consumed += c.to_lowercase().map(char::len_utf8).sum::<usize>();
+            skip
+        })
+        .collect();
+    rest.trim().to_string()
+}
+
+// 语言文件里没有的牌阵、位置数不对或没写解读要求的牌阵,用 zh-CN 的文字补上
+fn complete_spreads(mut text: LocaleText, base: &HashMap<String, SpreadText>) -> LocaleText {
+    for (name, base_text) in base {
+        let spread = text.spreads.entry(name.clone()).or_insert_with(|| base_text.clone());
+        if spread.positions.len() != base_text.positions.len() {
+            spread.positions = base_text.positions.clone();
+        }
+        if spread.guidance.is_empty() {
+            spread.guidance = base_text.guidance.clone();
+        }
+    }
+    text
+}
+
+fn index_cards(cards: Vec<TaroCard>) -> HashMap<u32, TaroCard> {
+    cards.into_iter().map(|card| (card.index, card)).collect()
+}
+
+// 语言文件和它的牌面翻译
+type LocaleFiles = (LocaleText, Vec<TaroCard>);
+
+fn read_locale(dir: &Path, code: &str) -> Result<Option<LocaleFiles>, Box<dyn Error + Send + Sync>> {
+    let path = dir.join(format!("{}.json", code));
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let text: LocaleText = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    let cards = match &text.cards {
+        Some(file) => serde_json::from_str(&std::fs::read_to_string(dir.join(file))?)?,
+        None => vec![],
+    };
+    Ok(Some((text, cards)))
+}
+
+// 语言目录里除自带语言以外的语言文件,牌面翻译文件 (*.cards.json) 不算
+fn extra_locale_codes(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| name.ends_with(".json") && !name.ends_with(".cards.json"))
+        .map(|name| name.trim_end_matches(".json").to_string())
+        .filter(|code| !BUNDLED_LOCALES.iter().any(|(bundled, _, _)| bundled == code))
+        .collect()
+}
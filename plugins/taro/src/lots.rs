@@ -0,0 +1,89 @@
+use crate::divination::{Casting, Oracle, load_table};
+use crate::locale::{Locale, fill};
+use crate::store::StoredCard;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+use std::path::Path;
+
+const BUNDLED_LOTS: &str = include_str!("../../../assets/divination/lots.json");
+const SPREAD_NAME: &str = "求签";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Lot {
+    pub number: u32,
+    // 上上、上吉、中吉、中平、下下
+    pub rank: String,
+    pub title: String,
+    pub poem: Vec<String>,
+    pub meaning: String,
+}
+
+// 求签: 从签筒里摇出一支签,记录里的 index 是签号
+pub struct Lots {
+    lots: Vec<Lot>,
+}
+
+impl Lots {
+    pub fn load(dir: &Path) -> Self {
+        Self {
+            lots: load_table(dir, "lots.json", BUNDLED_LOTS),
+        }
+    }
+}
+
+impl Oracle for Lots {
+    fn names(&self) -> &'static [&'static str] {
+        &["求签", "抽签", "灵签"]
+    }
+
+    fn prompt_key(&self) -> &'static str {
+        "lots"
+    }
+
+    fn owns(&self, spread: &str) -> bool {
+        spread == SPREAD_NAME
+    }
+
+    fn cast(&self, input: &str, rng: &mut StdRng) -> Casting {
+        let lot = self.lots.choose(rng).expect("lot table is not empty");
+        Casting {
+            spread: SPREAD_NAME.to_string(),
+            items: vec![StoredCard {
+                index: lot.number,
+                upright: true,
+            }],
+            question: input.trim().to_string(),
+        }
+    }
+
+    fn describe(&self, locale: &Locale, casting: &Casting) -> Vec<String> {
+        let text = &locale.text.lots;
+        casting
+            .items
+            .iter()
+            .filter_map(|item| self.lots.iter().find(|lot| lot.number == item.index))
+            .flat_map(|lot| {
+                vec![
+                    fill(
+                        &text.lot,
+                        &[("number", &lot.number.to_string()), ("rank", &lot.rank), ("title", &lot.title)],
+                    ),
+                    fill(&text.poem, &[("poem", &lot.poem.join(""))]),
+                    fill(&text.meaning, &[("meaning", &lot.meaning)]),
+                ]
+            })
+            .collect()
+    }
+
+    fn interpret_offline(&self, locale: &Locale, casting: &Casting) -> String {
+        let text = &locale.text.lots;
+        let mut lines = vec![text.offline_header.clone()];
+        if !casting.question.is_empty() {
+            lines.push(fill(&text.question, &[("question", &casting.question)]));
+        }
+        lines.push(String::new());
+        lines.extend(self.describe(locale, casting));
+        lines.join("\n")
+    }
+}
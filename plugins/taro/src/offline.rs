@@ -0,0 +1,91 @@
+use crate::deck::{Arcana, DrawnCard};
+use crate::locale::{Locale, fill};
+use crate::spread::Spread;
+
+// 离线解读: 模型不可用时,用牌义数据和牌阵位置拼出一份解读
+pub fn interpret(locale: &Locale, spread: &Spread, question: &str, cards: &[DrawnCard]) -> String {
+    let text = &locale.text.offline;
+    let mut lines: Vec<String> = vec![fill(&text.header, &[("spread", locale.spread_name(spread))])];
+    if !question.is_empty() {
+        lines.push(fill(&text.question, &[("question", question)]));
+    }
+    lines.push(String::new());
+
+    for (position, drawn) in locale.positions(spread).iter().zip(cards) {
+        let meaning = locale.meaning(drawn);
+        lines.push(fill(
+            &text.entry,
+            &[
+                ("position", position),
+                ("card", &locale.card(&drawn.card).name),
+                ("orientation", locale.orientation(drawn.upright)),
+                ("keywords", &locale.keywords(meaning)),
+                ("description", &meaning.description),
+            ],
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(summarize(locale, spread, cards));
+    lines.join("\n")
+}
+
+fn summarize(locale: &Locale, spread: &Spread, cards: &[DrawnCard]) -> String {
+    let text = &locale.text.offline;
+    let upright = cards.iter().filter(|drawn| drawn.upright).count();
+    let reversed = cards.len() - upright;
+    let majors = cards.iter().filter(|drawn| drawn.card.arcana == Arcana::Major).count();
+
+    let mut summary: Vec<String> = vec![];
+
+    // 是否牌阵直接按正逆位给答案
+    if spread.name == "是否" {
+        summary.push(if upright > reversed {
+            text.answer_yes.clone()
+        } else {
+            text.answer_no.clone()
+        });
+    } else if upright > reversed {
+        summary.push(text.mostly_upright.clone());
+    } else if upright < reversed {
+        summary.push(text.mostly_reversed.clone());
+    } else {
+        summary.push(text.balanced.clone());
+    }
+
+    if majors * 2 > cards.len() {
+        summary.push(text.many_majors.clone());
+    }
+
+    if let Some(theme) = dominant_suit(cards).and_then(|suit| text.themes.get(suit)) {
+        summary.push(fill(&text.suit_focus, &[("theme", theme)]));
+    }
+
+    if let Some(last) = cards.last() {
+        summary.push(fill(
+            &text.last_card,
+            &[
+                ("card", &locale.card(&last.card).name),
+                ("description", &locale.meaning(last).description),
+            ],
+        ));
+    }
+
+    summary.join(&locale.text.sentence_separator)
+}
+
+// 小阿卡纳里张数最多的花色,至少两张才算集中
+fn dominant_suit(cards: &[DrawnCard]) -> Option<&'static str> {
+    ["wands", "cups", "swords", "pentacles"]
+        .into_iter()
+        .map(|suit| {
+            let count = cards
+                .iter()
+                .filter(|drawn| drawn.card.suit.as_deref() == Some(suit))
+                .count();
+            (count, suit)
+        })
+        .filter(|(count, _)| *count >= 2)
+        .max_by_key(|(count, _)| *count)
+        .map(|(_, suit)| suit)
+}
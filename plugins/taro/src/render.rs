@@ -0,0 +1,153 @@
+use crate::deck::{DrawnCard, TaroCard};
+use crate::spread::Spread;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::error::Error;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+const CARD_WIDTH: u32 = 150;
+const CARD_HEIGHT: u32 = 260;
+const GAP: u32 = 20;
+const MARGIN: u32 = 30;
+const BACKGROUND: Rgba<u8> = Rgba([36, 24, 48, 255]);
+
+// 把牌阵画成一张图: 牌面图片放在 assets/tarot/cards/<index>.png(或 .jpg),
+// 没有图片的牌画成带编号的占位牌;逆位的牌旋转 180°。
+// 仓库里不带牌面图片(版权原因),默认全部是占位牌,放入图片后自动使用
+#[derive(Clone)]
+pub struct SpreadRenderer {
+    art_dir: PathBuf,
+}
+
+impl SpreadRenderer {
+    pub fn new(art_dir: PathBuf) -> Self {
+        Self { art_dir }
+    }
+
+    // 返回 PNG 编码后的图片
+    pub fn render(&self, spread: &Spread, cards: &[DrawnCard]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let slot_width = (CARD_WIDTH + GAP) as f32;
+        let slot_height = (CARD_HEIGHT + GAP) as f32;
+
+        let max_col = spread.layout.iter().map(|(col, _, _)| *col).fold(0.0, f32::max);
+        let max_row = spread.layout.iter().map(|(_, row, _)| *row).fold(0.0, f32::max);
+        let width = MARGIN * 2 + (max_col * slot_width) as u32 + CARD_WIDTH;
+        let height = MARGIN * 2 + (max_row * slot_height) as u32 + CARD_HEIGHT;
+
+        let mut canvas = RgbaImage::from_pixel(width, height, BACKGROUND);
+
+        for (drawn, ((x, y, _, _), (_, _, crossed))) in cards.iter().zip(card_rects(spread).into_iter().zip(spread.layout)) {
+            let mut face = self.card_face(&drawn.card);
+            if !drawn.upright {
+                face = imageops::rotate180(&face);
+            }
+            if *crossed {
+                face = imageops::rotate90(&face);
+            }
+            imageops::overlay(&mut canvas, &face, x as i64, y as i64);
+        }
+
+        let mut png = Vec::new();
+        canvas.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        Ok(png)
+    }
+
+    fn card_face(&self, card: &TaroCard) -> RgbaImage {
+        for extension in ["png", "jpg", "jpeg"] {
+            let path = self.art_dir.join(format!("{}.{}", card.index, extension));
+            if let Ok(art) = image::open(&path) {
+                return imageops::resize(&art.to_rgba8(), CARD_WIDTH, CARD_HEIGHT, FilterType::Triangle);
+            }
+        }
+        placeholder_face(card)
+    }
+}
+
+// 每个位置的牌在画布上占的矩形 (左, 上, 右, 下),以格子中心对齐,横放的牌也压在同一个中心上
+pub fn card_rects(spread: &Spread) -> Vec<(f32, f32, f32, f32)> {
+    let slot_width = (CARD_WIDTH + GAP) as f32;
+    let slot_height = (CARD_HEIGHT + GAP) as f32;
+    spread
+        .layout
+        .iter()
+        .map(|(col, row, crossed)| {
+            let (width, height) = if *crossed {
+                (CARD_HEIGHT as f32, CARD_WIDTH as f32)
+            } else {
+                (CARD_WIDTH as f32, CARD_HEIGHT as f32)
+            };
+            let center_x = MARGIN as f32 + col * slot_width + CARD_WIDTH as f32 / 2.0;
+            let center_y = MARGIN as f32 + row * slot_height + CARD_HEIGHT as f32 / 2.0;
+            (center_x - width / 2.0, center_y - height / 2.0, center_x + width / 2.0, center_y + height / 2.0)
+        })
+        .collect()
+}
+
+// 占位牌: 按花色上色的边框,中间画上牌的编号
+fn placeholder_face(card: &TaroCard) -> RgbaImage {
+    let border = match card.suit.as_deref() {
+        Some("wands") => Rgba([196, 84, 52, 255]),
+        Some("cups") => Rgba([64, 120, 200, 255]),
+        Some("swords") => Rgba([168, 176, 188, 255]),
+        Some("pentacles") => Rgba([72, 160, 96, 255]),
+        _ => Rgba([214, 176, 72, 255]),
+    };
+    let fill = Rgba([246, 238, 220, 255]);
+
+    let mut face = RgbaImage::from_pixel(CARD_WIDTH, CARD_HEIGHT, border);
+    let inset = 8;
+    for y in inset..CARD_HEIGHT - inset {
+        for x in inset..CARD_WIDTH - inset {
+            face.put_pixel(x, y, fill);
+        }
+    }
+
+    // 编号写在上半部分,逆位旋转后会出现在下方,一眼能看出正逆
+    draw_number(&mut face, card.index, CARD_HEIGHT / 4, border);
+    face
+}
+
+// 3x5 点阵数字
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+fn draw_number(image: &mut RgbaImage, number: u32, center_y: u32, color: Rgba<u8>) {
+    let scale = 8;
+    let digits: Vec<usize> = number
+        .to_string()
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|d| d as usize)
+        .collect();
+    let text_width = digits.len() as u32 * 4 * scale - scale;
+    let left = (image.width().saturating_sub(text_width)) / 2;
+    let top = center_y.saturating_sub(5 * scale / 2);
+
+    for (i, digit) in digits.iter().enumerate() {
+        for (row, bits) in DIGITS[*digit].iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                let x0 = left + (i as u32 * 4 + col) * scale;
+                let y0 = top + row as u32 * scale;
+                for y in y0..(y0 + scale).min(image.height()) {
+                    for x in x0..(x0 + scale).min(image.width()) {
+                        image.put_pixel(x, y, color);
+                    }
+                }
+            }
+        }
+    }
+}
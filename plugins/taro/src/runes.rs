@@ -0,0 +1,134 @@
+use crate::deck::CardMeaning;
+use crate::divination::{Casting, Oracle, load_table};
+use crate::locale::{Locale, fill};
+use crate::spread::{Spread, parse_spread_in};
+use crate::store::StoredCard;
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use std::path::Path;
+
+const BUNDLED_RUNES: &str = include_str!("../../../assets/divination/runes.json");
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Rune {
+    pub index: u32,
+    pub name: String,
+    pub upright: CardMeaning,
+    // 形状对称的符文没有逆位
+    pub reversed: Option<CardMeaning>,
+}
+
+// 符文牌阵沿用塔罗的 Spread,不出图,layout 只用来确定符文数
+pub const RUNE_SPREADS: &[Spread] = &[
+    Spread {
+        name: "三符",
+        aliases: &["三枚", "诺伦", "三张"],
+        layout: &[(0.0, 0.0, false), (1.0, 0.0, false), (2.0, 0.0, false)],
+    },
+    Spread {
+        name: "单符",
+        aliases: &["一枚", "单枚", "单张"],
+        layout: &[(0.0, 0.0, false)],
+    },
+];
+
+// 卢恩符文: 老弗萨克 24 符文,抽出的符文不重复
+pub struct Runes {
+    runes: Vec<Rune>,
+}
+
+impl Runes {
+    pub fn load(dir: &Path) -> Self {
+        Self {
+            runes: load_table(dir, "runes.json", BUNDLED_RUNES),
+        }
+    }
+
+    fn rune(&self, index: u32) -> Option<&Rune> {
+        self.runes.iter().find(|rune| rune.index == index)
+    }
+}
+
+impl Oracle for Runes {
+    fn names(&self) -> &'static [&'static str] {
+        &["符文", "卢恩", "如尼"]
+    }
+
+    fn prompt_key(&self) -> &'static str {
+        "runes"
+    }
+
+    fn owns(&self, spread: &str) -> bool {
+        RUNE_SPREADS.iter().any(|rune_spread| rune_spread.name == spread)
+    }
+
+    fn cast(&self, input: &str, rng: &mut StdRng) -> Casting {
+        let (spread, question) = parse_spread_in(RUNE_SPREADS, &RUNE_SPREADS[0], input);
+
+        let mut pile: Vec<&Rune> = self.runes.iter().collect();
+        pile.shuffle(rng);
+        let items = pile
+            .into_iter()
+            .take(spread.card_count())
+            .map(|rune| StoredCard {
+                index: rune.index,
+                upright: rune.reversed.is_none() || rng.random_bool(0.5),
+            })
+            .collect();
+
+        Casting {
+            spread: spread.name.to_string(),
+            items,
+            question,
+        }
+    }
+
+    fn describe(&self, locale: &Locale, casting: &Casting) -> Vec<String> {
+        let Some(spread) = RUNE_SPREADS.iter().find(|spread| spread.name == casting.spread) else {
+            return vec![];
+        };
+
+        let text = &locale.text.runes;
+        let mut lines = vec![fill(
+            &text.spread_line,
+            &[("spread", locale.spread_name(spread)), ("guidance", locale.spread_guidance(spread))],
+        )];
+        for (position, item) in locale.positions(spread).iter().zip(&casting.items) {
+            let Some(rune) = self.rune(item.index) else {
+                continue;
+            };
+            let meaning = match (&rune.reversed, item.upright) {
+                (Some(reversed), false) => reversed,
+                _ => &rune.upright,
+            };
+            let orientation = if rune.reversed.is_none() {
+                text.no_reversed.as_str()
+            } else {
+                locale.orientation(item.upright)
+            };
+            lines.push(fill(
+                &text.rune_line,
+                &[
+                    ("position", position),
+                    ("name", &rune.name),
+                    ("orientation", orientation),
+                    ("keywords", &locale.keywords(meaning)),
+                    ("description", &meaning.description),
+                ],
+            ));
+        }
+        lines
+    }
+
+    fn interpret_offline(&self, locale: &Locale, casting: &Casting) -> String {
+        let mut lines = vec![locale.text.runes.offline_header.clone()];
+        if !casting.question.is_empty() {
+            lines.push(fill(&locale.text.offline.question, &[("question", &casting.question)]));
+        }
+        lines.push(String::new());
+        lines.extend(self.describe(locale, casting));
+        lines.join("\n")
+    }
+}
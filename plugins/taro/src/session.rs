@@ -0,0 +1,61 @@
+use crate::llm::Message;
+use crate::store::StoredCard;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// 一次占卜的上下文: 牌阵名、牌(或卦、签)和到目前为止的对话,和历史记录的格式一致
+#[derive(Clone)]
+pub struct ReadingSession {
+    pub spread: String,
+    pub cards: Vec<StoredCard>,
+    pub messages: Vec<Message>,
+    // 机器人发出的解读和追问回答,回复这些消息才算追问
+    pub message_ids: Vec<i32>,
+    expires_at: Instant,
+}
+
+// 会话按 (群, 用户) 区分,私聊的群为 None
+type SessionKey = (Option<i64>, i64);
+
+// 每个用户在每个群里只保留最近一次占卜,过期后追问需要带上记录编号
+pub struct SessionStore {
+    ttl: Duration,
+    sessions: Mutex<HashMap<SessionKey, ReadingSession>>,
+}
+
+impl SessionStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, group_id: Option<i64>, user_id: i64) -> Option<ReadingSession> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.get(&(group_id, user_id)).cloned()
+    }
+
+    // 写入或刷新会话,有效期从现在重新计算
+    pub fn put(
+        &self,
+        group_id: Option<i64>,
+        user_id: i64,
+        spread: String,
+        cards: Vec<StoredCard>,
+        messages: Vec<Message>,
+        message_ids: Vec<i32>,
+    ) {
+        let session = ReadingSession {
+            spread,
+            cards,
+            messages,
+            message_ids,
+            expires_at: Instant::now() + self.ttl,
+        };
+        self.sessions.lock().unwrap().insert((group_id, user_id), session);
+    }
+}
@@ -0,0 +1,104 @@
+// 牌阵: 每个位置对应一张牌;位置名和给模型的解读要求在语言文件的 spreads 里,按牌阵名查找
+#[derive(Debug)]
+pub struct Spread {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    // 出图时每个位置的 (列, 行, 是否横放),单位是一张牌的格子
+    pub layout: &'static [(f32, f32, bool)],
+}
+
+impl Spread {
+    pub fn card_count(&self) -> usize {
+        self.layout.len()
+    }
+
+    fn matches(&self, word: &str) -> bool {
+        self.name == word || self.aliases.contains(&word)
+    }
+}
+
+pub const SPREADS: &[Spread] = &[
+    Spread {
+        name: "时间之流",
+        aliases: &["三张牌", "过去现在未来", "三张"],
+        layout: &[(0.0, 0.0, false), (1.0, 0.0, false), (2.0, 0.0, false)],
+    },
+    Spread {
+        name: "单张",
+        aliases: &["单张牌", "一张", "每日一牌"],
+        layout: &[(0.0, 0.0, false)],
+    },
+    Spread {
+        name: "凯尔特十字",
+        aliases: &["凯尔特", "十字"],
+        // 左边是十字,第二张横压在第一张上,横放的牌比一格宽,左右两张要让开;
+        // 右边是从下往上的权杖列
+        layout: &[
+            (1.4, 1.5, false),
+            (1.4, 1.5, true),
+            (1.4, 0.5, false),
+            (1.4, 2.5, false),
+            (0.0, 1.5, false),
+            (2.8, 1.5, false),
+            (4.3, 3.0, false),
+            (4.3, 2.0, false),
+            (4.3, 1.0, false),
+            (4.3, 0.0, false),
+        ],
+    },
+    Spread {
+        name: "关系",
+        aliases: &["关系牌阵", "恋人牌阵", "感情"],
+        layout: &[
+            (0.0, 0.0, false),
+            (2.0, 0.0, false),
+            (0.0, 1.0, false),
+            (2.0, 1.0, false),
+            (1.0, 0.0, false),
+            (1.0, 1.0, false),
+            (1.0, 2.0, false),
+        ],
+    },
+    Spread {
+        name: "是否",
+        aliases: &["是否牌阵", "是不是", "yesno"],
+        layout: &[(0.0, 0.0, false)],
+    },
+];
+
+// 默认使用时间之流牌阵
+pub fn default_spread() -> &'static Spread {
+    &SPREADS[0]
+}
+
+// 解析 `运势` 后面的内容: 第一个词是牌阵名时选用该牌阵,剩下的是问题
+pub fn parse_spread(input: &str) -> (&'static Spread, String) {
+    parse_spread_in(SPREADS, default_spread(), input)
+}
+
+// 在给定的牌阵里解析,符文等其他占卜也用这套牌阵
+pub fn parse_spread_in(
+    spreads: &'static [Spread],
+    default: &'static Spread,
+    input: &str,
+) -> (&'static Spread, String) {
+    let input = input.trim();
+    let (first_word, rest) = input
+        .split_once(char::is_whitespace)
+        .unwrap_or((input, ""));
+
+    match spreads.iter().find(|spread| spread.matches(first_word)) {
+        Some(spread) => (spread, rest.trim().to_string()),
+        None => (default, input.to_string()),
+    }
+}
+
+// 按保存的牌阵名找回牌阵
+pub fn find_spread(name: &str) -> Option<&'static Spread> {
+    SPREADS.iter().find(|spread| spread.name == name)
+}
+
+// 今日运势出图用单张牌阵
+pub fn daily_spread() -> &'static Spread {
+    &SPREADS[1]
+}
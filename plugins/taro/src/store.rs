@@ -0,0 +1,307 @@
+use kovi::tokio;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct DailyFortune {
+    pub user_id: i64,
+    pub date: String,
+    pub card_index: u32,
+    pub upright: bool,
+    pub score: u32,
+    pub reading: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DailyRank {
+    pub user_id: i64,
+    pub nickname: String,
+    pub card_index: u32,
+    pub upright: bool,
+    pub score: u32,
+}
+
+// 历史记录里的一张牌,牌面内容以当前牌组数据为准
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredCard {
+    pub index: u32,
+    pub upright: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadingRecord {
+    pub id: i64,
+    pub user_id: i64,
+    pub group_id: Option<i64>,
+    pub created_at: String,
+    pub spread: String,
+    pub cards: Vec<StoredCard>,
+    pub question: String,
+    pub interpretation: String,
+}
+
+impl ReadingRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let cards: String = row.get(5)?;
+        Ok(Self {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            group_id: row.get(2)?,
+            created_at: row.get(3)?,
+            spread: row.get(4)?,
+            cards: serde_json::from_str(&cards).unwrap_or_default(),
+            question: row.get(6)?,
+            interpretation: row.get(7)?,
+        })
+    }
+}
+
+// 塔罗插件的 SQLite 存储
+#[derive(Clone)]
+pub struct TaroStore {
+    path: PathBuf,
+}
+
+impl TaroStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn open(&self) -> Result<Connection, Box<dyn Error + Send + Sync>> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS daily_fortunes (
+                user_id INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                card_index INTEGER NOT NULL,
+                upright INTEGER NOT NULL,
+                score INTEGER NOT NULL,
+                reading TEXT NOT NULL,
+                PRIMARY KEY (user_id, date)
+            );
+            CREATE TABLE IF NOT EXISTS daily_group_members (
+                group_id INTEGER NOT NULL,
+                user_id INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                nickname TEXT NOT NULL,
+                PRIMARY KEY (group_id, user_id, date)
+            );
+            CREATE TABLE IF NOT EXISTS readings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                group_id INTEGER,
+                created_at TEXT NOT NULL,
+                spread TEXT NOT NULL,
+                cards TEXT NOT NULL,
+                question TEXT NOT NULL,
+                interpretation TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS readings_user ON readings (user_id, id);
+            CREATE TABLE IF NOT EXISTS group_settings (
+                group_id INTEGER PRIMARY KEY,
+                language TEXT NOT NULL
+            );",
+        )?;
+        Ok(conn)
+    }
+
+    pub async fn get_daily(&self, user_id: i64, date: &str) -> Result<Option<DailyFortune>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let date = date.to_string();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let fortune = conn
+                .query_row(
+                    "SELECT user_id, date, card_index, upright, score, reading
+                     FROM daily_fortunes WHERE user_id = ?1 AND date = ?2",
+                    params![user_id, date],
+                    |row| {
+                        Ok(DailyFortune {
+                            user_id: row.get(0)?,
+                            date: row.get(1)?,
+                            card_index: row.get(2)?,
+                            upright: row.get(3)?,
+                            score: row.get(4)?,
+                            reading: row.get(5)?,
+                        })
+                    },
+                )
+                .optional()?;
+            Ok(fortune)
+        })
+        .await?
+    }
+
+    // 同一天重复写入时保留第一次的结果
+    pub async fn save_daily(&self, fortune: DailyFortune) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            conn.execute(
+                "INSERT OR IGNORE INTO daily_fortunes (user_id, date, card_index, upright, score, reading)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    fortune.user_id,
+                    fortune.date,
+                    fortune.card_index,
+                    fortune.upright,
+                    fortune.score,
+                    fortune.reading
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    // 记录用户当天在哪个群抽过,排行按群统计
+    pub async fn join_daily_rank(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        date: &str,
+        nickname: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let date = date.to_string();
+        let nickname = nickname.to_string();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            conn.execute(
+                "INSERT OR REPLACE INTO daily_group_members (group_id, user_id, date, nickname)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![group_id, user_id, date, nickname],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn daily_ranking(
+        &self,
+        group_id: i64,
+        date: &str,
+        limit: u32,
+    ) -> Result<Vec<DailyRank>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let date = date.to_string();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let mut stmt = conn.prepare(
+                "SELECT m.user_id, m.nickname, f.card_index, f.upright, f.score
+                 FROM daily_group_members m
+                 JOIN daily_fortunes f ON f.user_id = m.user_id AND f.date = m.date
+                 WHERE m.group_id = ?1 AND m.date = ?2
+                 ORDER BY f.score DESC, m.user_id
+                 LIMIT ?3",
+            )?;
+            let ranking = stmt
+                .query_map(params![group_id, date, limit], |row| {
+                    Ok(DailyRank {
+                        user_id: row.get(0)?,
+                        nickname: row.get(1)?,
+                        card_index: row.get(2)?,
+                        upright: row.get(3)?,
+                        score: row.get(4)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(ranking)
+        })
+        .await?
+    }
+
+    // 保存一次占卜,返回记录编号
+    pub async fn add_reading(&self, record: ReadingRecord) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            conn.execute(
+                "INSERT INTO readings (user_id, group_id, created_at, spread, cards, question, interpretation)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    record.user_id,
+                    record.group_id,
+                    record.created_at,
+                    record.spread,
+                    serde_json::to_string(&record.cards)?,
+                    record.question,
+                    record.interpretation
+                ],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await?
+    }
+
+    pub async fn recent_readings(&self, user_id: i64, limit: u32) -> Result<Vec<ReadingRecord>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let mut stmt = conn.prepare(
+                "SELECT id, user_id, group_id, created_at, spread, cards, question, interpretation
+                 FROM readings WHERE user_id = ?1 ORDER BY id DESC LIMIT ?2",
+            )?;
+            let readings = stmt
+                .query_map(params![user_id, limit], ReadingRecord::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(readings)
+        })
+        .await?
+    }
+
+    // 只能查到自己的记录
+    pub async fn get_reading(&self, user_id: i64, id: i64) -> Result<Option<ReadingRecord>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let reading = conn
+                .query_row(
+                    "SELECT id, user_id, group_id, created_at, spread, cards, question, interpretation
+                     FROM readings WHERE id = ?1 AND user_id = ?2",
+                    params![id, user_id],
+                    ReadingRecord::from_row,
+                )
+                .optional()?;
+            Ok(reading)
+        })
+        .await?
+    }
+
+    pub async fn group_language(&self, group_id: i64) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            let language = conn
+                .query_row(
+                    "SELECT language FROM group_settings WHERE group_id = ?1",
+                    params![group_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(language)
+        })
+        .await?
+    }
+
+    pub async fn set_group_language(&self, group_id: i64, language: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let language = language.to_string();
+        tokio::task::spawn_blocking(move || {
+            let conn = store.open()?;
+            conn.execute(
+                "INSERT INTO group_settings (group_id, language) VALUES (?1, ?2)
+                 ON CONFLICT(group_id) DO UPDATE SET language = excluded.language",
+                params![group_id, language],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+}
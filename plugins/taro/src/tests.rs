@@ -0,0 +1,350 @@
+// 不依赖机器人和网络的单元测试: 抽牌复现等
+use super::*;
+use std::path::Path;
+
+fn deck(mode: DeckMode) -> Deck {
+    Deck::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/tarot/cards.json"), mode)
+}
+
+fn draw(deck: &Deck, seed: u64) -> Vec<(u32, bool)> {
+    deck.draw(10, &mut reading_rng(Some(seed)))
+        .into_iter()
+        .map(|drawn| (drawn.card.index, drawn.upright))
+        .collect()
+}
+
+#[test]
+fn same_seed_reproduces_cards_and_orientations() {
+    let deck = deck(DeckMode::Full);
+    assert_eq!(deck.cards.len(), 78);
+    assert_eq!(draw(&deck, 42), draw(&deck, 42));
+    assert_ne!(draw(&deck, 42), draw(&deck, 43));
+
+    // 同一个随机数生成器连续占卜,每次的结果不同但整体可复现
+    let mut first = reading_rng(Some(7));
+    let mut second = reading_rng(Some(7));
+    for _ in 0..3 {
+        let a: Vec<u32> = deck.draw(3, &mut first).iter().map(|drawn| drawn.card.index).collect();
+        let b: Vec<u32> = deck.draw(3, &mut second).iter().map(|drawn| drawn.card.index).collect();
+        assert_eq!(a, b);
+    }
+}
+
+#[test]
+fn draws_never_repeat_a_card() {
+    let deck = deck(DeckMode::MajorOnly);
+    assert_eq!(deck.cards.len(), 22);
+    let mut indexes: Vec<u32> = draw(&deck, 1).into_iter().map(|(index, _)| index).collect();
+    indexes.sort();
+    indexes.dedup();
+    assert_eq!(indexes.len(), 10);
+}
+
+#[test]
+fn spread_layouts_do_not_overlap() {
+    let overlaps = |a: &(f32, f32, f32, f32), b: &(f32, f32, f32, f32)| a.0 < b.2 && b.0 < a.2 && a.1 < b.3 && b.1 < a.3;
+    for spread in spread::SPREADS {
+        let rects = render::card_rects(spread);
+        for i in 0..rects.len() {
+            for j in i + 1..rects.len() {
+                // 横放的牌本来就压在前一张上
+                let crossing = j == i + 1 && spread.layout[j].2;
+                assert!(crossing || !overlaps(&rects[i], &rects[j]), "{} 的第 {} 和第 {} 张牌重叠", spread.name, i + 1, j + 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn renders_placeholder_faces_for_every_spread() {
+    let deck = deck(DeckMode::Full);
+    let renderer = SpreadRenderer::new(PathBuf::from("assets/tarot/cards/missing"));
+    for spread in spread::SPREADS {
+        let cards = deck.draw(spread.card_count(), &mut reading_rng(Some(1)));
+        let png = renderer.render(spread, &cards).expect("render spread");
+        assert!(png.starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+}
+
+#[test]
+fn sessions_are_kept_per_group_and_user() {
+    let sessions = SessionStore::new(Duration::from_secs(60));
+    sessions.put(Some(1), 10, "单张牌".to_string(), vec![], vec![], vec![100]);
+    sessions.put(None, 10, "三张牌".to_string(), vec![], vec![], vec![200, 201]);
+
+    assert_eq!(sessions.get(Some(1), 10).unwrap().message_ids, vec![100]);
+    assert_eq!(sessions.get(None, 10).unwrap().spread, "三张牌");
+    assert!(sessions.get(Some(2), 10).is_none());
+    assert!(sessions.get(Some(1), 11).is_none());
+}
+
+#[test]
+fn spreads_are_chosen_by_locale_aliases() {
+    let locales = Locales::load(Path::new("assets/tarot/locales/missing"), "zh-CN");
+    let english = locales.find("en").unwrap();
+
+    let (spread, question) = english.parse_spread("Celtic Cross  will I pass the exam?");
+    assert_eq!((spread.name, question.as_str()), ("凯尔特十字", "will I pass the exam?"));
+    let (spread, question) = english.parse_spread("yes or no");
+    assert_eq!((spread.name, question.as_str()), ("是否", ""));
+    // 中文牌阵名在任何语言下都能用,没有牌阵名时整句都是问题
+    assert_eq!(english.parse_spread("凯尔特 问题").0.name, "凯尔特十字");
+    let (spread, question) = english.parse_spread("celticism is my question");
+    assert_eq!((spread.name, question.as_str()), ("时间之流", "celticism is my question"));
+    assert_eq!(locales.default_locale().parse_spread("Celtic Cross").0.name, "时间之流");
+}
+
+#[test]
+fn every_locale_has_text_for_every_spread() {
+    let dir = tempfile::tempdir().unwrap();
+    // 只翻译了牌阵名的语言文件,位置名和解读要求用 zh-CN 补上
+    let mut partial: serde_json::Value =
+        serde_json::from_str(include_str!("../../../assets/tarot/locales/en.json")).unwrap();
+    partial["code"] = "en-partial".into();
+    partial["aliases"] = serde_json::json!([]);
+    partial["cards"] = serde_json::Value::Null;
+    partial["spreads"] = serde_json::json!({ "关系": { "name": "Relationship", "positions": ["Only one"] } });
+    std::fs::write(dir.path().join("en-partial.json"), partial.to_string()).unwrap();
+
+    let locales = Locales::load(dir.path(), "zh-CN");
+    let spreads = spread::SPREADS.iter().chain(runes::RUNE_SPREADS);
+    for code in ["zh-CN", "en", "en-partial"] {
+        let locale = locales.find(code).unwrap();
+        for spread in spreads.clone() {
+            assert_eq!(locale.positions(spread).len(), spread.card_count(), "{} {}", code, spread.name);
+            assert!(!locale.spread_guidance(spread).is_empty(), "{} {}", code, spread.name);
+        }
+    }
+
+    let english = locales.find("en").unwrap();
+    let celtic = spread::find_spread("凯尔特十字").unwrap();
+    assert_eq!(english.positions(celtic)[0], "Present situation");
+    assert_eq!(locales.default_locale().positions(celtic)[0], "现状");
+    let partial = locales.find("en-partial").unwrap();
+    let relationship = spread::find_spread("关系").unwrap();
+    assert_eq!(partial.spread_name(relationship), "Relationship");
+    assert_eq!(partial.positions(relationship)[0], "你的状态");
+    assert_eq!(partial.spread_name(celtic), "凯尔特十字");
+}
+
+#[test]
+fn iching_text_follows_the_locale() {
+    let locales = Locales::load(Path::new("assets/tarot/locales/missing"), "zh-CN");
+    let english = locales.find("en").unwrap();
+    let iching = iching::IChing::load(Path::new("assets/divination/missing"));
+    let casting = Casting {
+        spread: "易经".to_string(),
+        items: [9, 7, 8, 8, 7, 6].iter().map(|&index| StoredCard { index, upright: true }).collect(),
+        question: "换工作".to_string(),
+    };
+
+    let offline = iching.interpret_offline(english, &casting);
+    assert!(offline.starts_with("(Offline reading) I Ching\nQuestion: 换工作"), "{}", offline);
+    assert!(offline.contains("Primary hexagram: No. "));
+    assert!(offline.contains("Changing lines: 初九, 上六"));
+    assert!(offline.contains("Resulting hexagram: "));
+    assert!(!offline.contains("本卦") && !offline.contains("变爻"));
+
+    let chinese = iching.interpret_offline(locales.default_locale(), &casting);
+    assert!(chinese.contains("变爻: 初九、上六"));
+    assert_eq!(english.record_spread_name("易经"), "I Ching");
+    assert_eq!(english.record_spread_name("凯尔特十字"), "Celtic Cross");
+}
+
+#[test]
+fn runes_and_lots_follow_the_locale() {
+    let locales = Locales::load(Path::new("assets/tarot/locales/missing"), "zh-CN");
+    let english = locales.find("en").unwrap();
+    let runes = runes::Runes::load(Path::new("assets/divination/missing"));
+    let rune_casting = Casting {
+        spread: "三符".to_string(),
+        items: vec![
+            StoredCard { index: 0, upright: true },
+            StoredCard { index: 1, upright: false },
+            StoredCard { index: 2, upright: true },
+        ],
+        question: "换工作".to_string(),
+    };
+
+    let offline = runes.interpret_offline(english, &rune_casting);
+    assert!(offline.starts_with("(Offline reading) Runes\nQuestion: 换工作"), "{}", offline);
+    assert!(offline.contains("Rune spread: Three Norns. "));
+    assert!(offline.contains("Position: Past (Urd), rune: ᚠ Fehu"));
+    assert!(offline.contains("Position: Present (Verdandi), rune: ᚢ Uruz（野牛）, reversed"), "{}", offline);
+    assert!(!offline.contains("位置") && !offline.contains("乌尔德"));
+    let chinese = runes.interpret_offline(locales.default_locale(), &rune_casting);
+    assert!(chinese.contains("位置: 过去(乌尔德), 符文: ᚠ Fehu"), "{}", chinese);
+
+    let lots = lots::Lots::load(Path::new("assets/divination/missing"));
+    let lot_casting = Casting {
+        spread: "求签".to_string(),
+        items: vec![StoredCard { index: 1, upright: true }],
+        question: "考试".to_string(),
+    };
+    let offline = lots.interpret_offline(english, &lot_casting);
+    assert!(offline.starts_with("(Offline reading) Fortune sticks\nAsking about: 考试"), "{}", offline);
+    assert!(offline.contains("Stick No. 1, 上上 [春风得意]"));
+    assert!(offline.contains("Verse: 春风吹绿柳"));
+    assert!(!offline.contains("签诗") && !offline.contains("解签"));
+    let chinese = lots.interpret_offline(locales.default_locale(), &lot_casting);
+    assert!(chinese.contains("第1签 上上 【春风得意】"), "{}", chinese);
+}
+
+fn drawn(deck: &Deck, index: u32, upright: bool) -> DrawnCard {
+    DrawnCard {
+        card: deck.card(index).expect("card in deck").clone(),
+        upright,
+    }
+}
+
+#[test]
+fn offline_reading_follows_fixed_draws_in_each_locale() {
+    let deck = deck(DeckMode::Full);
+    let locales = Locales::load(Path::new("assets/tarot/locales/missing"), "zh-CN");
+    let english = locales.find("en").unwrap();
+    let chinese = locales.default_locale();
+    let three = spread::find_spread("时间之流").unwrap();
+    // 愚者正位、圣杯王牌逆位、圣杯二逆位: 逆位占多数,牌面集中在圣杯
+    let cards = vec![drawn(&deck, 0, true), drawn(&deck, 36, false), drawn(&deck, 37, false)];
+
+    let reading = offline::interpret(chinese, three, "换工作", &cards);
+    let lines: Vec<&str> = reading.lines().collect();
+    assert_eq!(lines[..3], ["（离线解读）牌阵: 时间之流", "问题: 换工作", ""]);
+    assert_eq!(lines[3], "【过去】愚者（The Fool） 正位");
+    assert!(reading.contains("【现在】圣杯王牌（Ace of Cups） 反位\n关键词: "), "{}", reading);
+    assert!(reading.contains(&cards[1].card.reversed.description));
+    let summary = lines.last().unwrap();
+    assert!(summary.starts_with("逆位牌占多数"), "{}", summary);
+    assert!(summary.contains("牌面集中在感情与人际关系"));
+    assert!(summary.ends_with(&format!("最后一张圣杯二（Two of Cups）提示: {}", cards[2].card.reversed.description)));
+    assert!(!summary.contains("大阿卡纳较多"));
+
+    let reading = offline::interpret(english, three, "", &cards);
+    let lines: Vec<&str> = reading.lines().collect();
+    assert_eq!(lines[..2], ["(Offline reading) Spread: Past, Present, Future", ""]);
+    assert_eq!(lines[2], "[Past] The Fool, upright");
+    assert!(reading.contains("[Present] Ace of Cups, reversed\nKeywords: "), "{}", reading);
+    let summary = lines.last().unwrap();
+    assert!(summary.starts_with("Most cards are reversed"), "{}", summary);
+    assert!(summary.contains("The cards gather around feelings and relationships"));
+    assert!(summary.contains("The final card, Two of Cups, suggests: "));
+    assert!(!reading.contains("圣杯") && !reading.contains("逆位"));
+}
+
+#[test]
+fn offline_yes_no_answer_follows_the_orientation() {
+    let deck = deck(DeckMode::Full);
+    let locales = Locales::load(Path::new("assets/tarot/locales/missing"), "zh-CN");
+    let english = locales.find("en").unwrap();
+    let chinese = locales.default_locale();
+    let yes_no = spread::find_spread("是否").unwrap();
+
+    let upright = offline::interpret(chinese, yes_no, "能通过考试吗", &[drawn(&deck, 1, true)]);
+    assert!(upright.contains("【答案】魔术师（The Magician） 正位"), "{}", upright);
+    assert!(upright.lines().last().unwrap().starts_with("总体来看答案偏向“是”。"));
+    // 只有一张大阿卡纳也算大阿卡纳较多
+    assert!(upright.contains("大阿卡纳较多"));
+    let reversed = offline::interpret(chinese, yes_no, "能通过考试吗", &[drawn(&deck, 1, false)]);
+    assert!(reversed.lines().last().unwrap().starts_with("总体来看答案偏向“否”。"));
+
+    let reversed = offline::interpret(english, yes_no, "Will I pass?", &[drawn(&deck, 1, false)]);
+    assert!(reversed.starts_with("(Offline reading) Spread: Yes or No\nQuestion: Will I pass?"), "{}", reversed);
+    assert!(reversed.contains("[Answer] The Magician, reversed"));
+    assert!(reversed.lines().last().unwrap().starts_with("Overall, the answer leans towards \"no\"."));
+}
+
+fn store() -> (TaroStore, tempfile::TempDir) {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    (TaroStore::new(dir.path().join("taro.db")), dir)
+}
+
+fn fortune(user_id: i64, date: &str, score: u32) -> DailyFortune {
+    DailyFortune {
+        user_id,
+        date: date.to_string(),
+        card_index: user_id as u32 % 22,
+        upright: score >= 50,
+        score,
+        reading: format!("{} 的运势", user_id),
+    }
+}
+
+#[test]
+fn daily_draw_is_fixed_per_user_and_date() {
+    let deck = deck(DeckMode::Full);
+    assert_eq!(daily::daily_seed(10001, "2024-05-01"), daily::daily_seed(10001, "2024-05-01"));
+    assert_ne!(daily::daily_seed(10001, "2024-05-01"), daily::daily_seed(10001, "2024-05-02"));
+    assert_ne!(daily::daily_seed(10001, "2024-05-01"), daily::daily_seed(10002, "2024-05-01"));
+
+    let daily = |user_id: i64, date: &str| {
+        let (drawn, score) = daily::draw_daily(&deck, user_id, date);
+        (drawn.card.index, drawn.upright, score)
+    };
+    assert_eq!(daily(10001, "2024-05-01"), daily(10001, "2024-05-01"));
+    // 一个月里每天的牌和分数不会都一样
+    let month: Vec<_> = (1..=30).map(|day| daily(10001, &format!("2024-05-{:02}", day))).collect();
+    assert!(month.iter().any(|draw| *draw != month[0]));
+    assert!(month.iter().all(|(_, _, score)| *score <= 100));
+}
+
+#[tokio::test]
+async fn daily_ranking_is_ordered_by_score_within_the_group() {
+    let (store, _dir) = store();
+    let date = "2024-05-01";
+    for (user_id, score) in [(10001, 35), (10002, 92), (10003, 60), (10004, 92)] {
+        store.save_daily(fortune(user_id, date, score)).await.unwrap();
+        store.join_daily_rank(30000, user_id, date, &format!("成员{}", user_id)).await.unwrap();
+    }
+    // 别的群和别的日期不算进来
+    store.save_daily(fortune(10005, date, 99)).await.unwrap();
+    store.join_daily_rank(30001, 10005, date, "别的群").await.unwrap();
+    store.save_daily(fortune(10001, "2024-05-02", 100)).await.unwrap();
+
+    let ranking = store.daily_ranking(30000, date, 10).await.unwrap();
+    let order: Vec<(i64, u32)> = ranking.iter().map(|rank| (rank.user_id, rank.score)).collect();
+    assert_eq!(order, [(10002, 92), (10004, 92), (10003, 60), (10001, 35)]);
+    assert_eq!(store.daily_ranking(30000, date, 2).await.unwrap().len(), 2);
+
+    let locales = Locales::load(Path::new("assets/tarot/locales/missing"), "zh-CN");
+    let text = daily::format_ranking(locales.default_locale(), &ranking, &deck(DeckMode::Full));
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].starts_with("1. 成员10002 - 92"), "{}", text);
+    assert!(lines[4].starts_with("4. 成员10001 - 35"), "{}", text);
+}
+
+#[tokio::test]
+async fn reading_history_round_trips_through_the_store() {
+    let (store, _dir) = store();
+    let record = |user_id: i64, question: &str| ReadingRecord {
+        id: 0,
+        user_id,
+        group_id: Some(30000),
+        created_at: "2024-05-01 12:00:00".to_string(),
+        spread: "时间之流".to_string(),
+        cards: vec![
+            StoredCard { index: 0, upright: true },
+            StoredCard { index: 36, upright: false },
+            StoredCard { index: 77, upright: true },
+        ],
+        question: question.to_string(),
+        interpretation: "解读".to_string(),
+    };
+
+    let first = store.add_reading(record(10001, "第一次")).await.unwrap();
+    let second = store.add_reading(record(10001, "第二次")).await.unwrap();
+    let other = store.add_reading(record(10002, "别人的")).await.unwrap();
+
+    let recent = store.recent_readings(10001, 10).await.unwrap();
+    let questions: Vec<&str> = recent.iter().map(|record| record.question.as_str()).collect();
+    assert_eq!(questions, ["第二次", "第一次"]);
+    assert_eq!(store.recent_readings(10001, 1).await.unwrap()[0].id, second);
+
+    let saved = store.get_reading(10001, first).await.unwrap().expect("own reading");
+    assert_eq!((saved.user_id, saved.group_id), (10001, Some(30000)));
+    assert_eq!((saved.spread.as_str(), saved.interpretation.as_str()), ("时间之流", "解读"));
+    let cards: Vec<(u32, bool)> = saved.cards.iter().map(|card| (card.index, card.upright)).collect();
+    assert_eq!(cards, [(0, true), (36, false), (77, true)]);
+    // 只能查到自己的记录
+    assert!(store.get_reading(10001, other).await.unwrap().is_none());
+}
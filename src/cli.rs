@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+const USAGE: &str = "用法:
+  my-kovi-bot                                 启动机器人
+  my-kovi-bot export-quotes <群号> <文件>     把群的语录本导出为 tar 包
+  my-kovi-bot import-quotes <群号> <文件>     把语录包导入到指定的群";
+
+// 带参数启动时执行子命令而不是启动机器人,返回进程退出码
+pub fn run(args: &[String]) -> i32 {
+    let (command, group_id, path) = match args {
+        [command, group_id, path] => match group_id.parse::<i64>() {
+            Ok(group_id) => (command.as_str(), group_id, PathBuf::from(path)),
+            Err(_) => {
+                eprintln!("群号不对: {}\n{}", group_id, USAGE);
+                return 2;
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    let runtime = match kovi::tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("创建运行时失败: {:?}", e);
+            return 1;
+        }
+    };
+    let result = match command {
+        "export-quotes" => runtime.block_on(export_quotes(group_id, path)),
+        "import-quotes" => runtime.block_on(import_quotes(group_id, path)),
+        _ => {
+            eprintln!("不认识的命令: {}\n{}", command, USAGE);
+            return 2;
+        }
+    };
+    match result {
+        Ok(summary) => {
+            println!("{}", summary);
+            0
+        }
+        Err(e) => {
+            eprintln!("{} 失败: {}", command, e);
+            1
+        }
+    }
+}
+
+type CommandResult = Result<String, Box<dyn std::error::Error + Send + Sync>>;
+
+#[cfg(feature = "history-book")]
+async fn export_quotes(group_id: i64, path: PathBuf) -> CommandResult {
+    history_book::export_quotes(group_id, &path).await
+}
+
+#[cfg(feature = "history-book")]
+async fn import_quotes(group_id: i64, path: PathBuf) -> CommandResult {
+    history_book::import_quotes(group_id, &path).await
+}
+
+#[cfg(not(feature = "history-book"))]
+async fn export_quotes(_group_id: i64, _path: PathBuf) -> CommandResult {
+    Err("没有编译 history-book 插件".into())
+}
+
+#[cfg(not(feature = "history-book"))]
+async fn import_quotes(_group_id: i64, _path: PathBuf) -> CommandResult {
+    Err("没有编译 history-book 插件".into())
+}
@@ -1,6 +1,39 @@
 use kovi::build_bot;
+use std::env;
+use std::path::PathBuf;
+
+mod cli;
+mod plugins;
+
+use plugins::{PluginList, mount_plugins};
 
 fn main() {
-    let bot = build_bot!(taro, deepseek);
+    // 带参数时是导出导入语录之类的子命令,执行完就退出
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
+    // 启用哪些插件写在 plugins.toml,新增插件只需要在下面登记一次
+    let config_path = env::var("PLUGINS_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("plugins.toml"));
+    // 所有插件 feature 都关掉时下面两个变量不会被修改
+    #[allow(unused_mut)]
+    let mut plugin_list = PluginList::load(&config_path);
+    #[allow(unused_mut)]
+    let mut bot = build_bot!();
+    mount_plugins!(bot, plugin_list, [
+        #[cfg(feature = "base")]
+        base,
+        #[cfg(feature = "deepseek")]
+        deepseek,
+        #[cfg(feature = "taro")]
+        taro,
+        #[cfg(feature = "history-book")]
+        history_book,
+        #[cfg(feature = "shindan-maker")]
+        kovi_plugin_shindan_maker,
+    ]);
     bot.run();
 }
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct PluginsFile {
+    plugins: Vec<String>,
+}
+
+// 启动时要挂载的插件,没有配置文件时挂载所有编译进来的插件
+pub struct PluginList {
+    enabled: Option<Vec<String>>,
+    mounted: HashSet<String>,
+}
+
+impl PluginList {
+    pub fn load(path: &Path) -> Self {
+        let enabled = match read_plugins_file(path) {
+            Ok(Some(file)) => Some(file.plugins.iter().map(|name| normalize(name)).collect()),
+            Ok(None) => {
+                eprintln!("没有找到 {}, 挂载所有编译进来的插件", path.display());
+                None
+            }
+            Err(e) => {
+                eprintln!("读取 {} 失败, 挂载所有编译进来的插件: {:?}", path.display(), e);
+                None
+            }
+        };
+        Self {
+            enabled,
+            mounted: HashSet::new(),
+        }
+    }
+
+    // 插件名里的 - 和 _ 视为相同,history-book 和 history_book 都可以
+    // 所有插件都没编译进来时不会用到
+    #[allow(dead_code)]
+    pub fn mounts(&mut self, name: &str) -> bool {
+        let name = normalize(name);
+        let mounts = self.enabled.as_ref().is_none_or(|enabled| enabled.contains(&name));
+        if mounts {
+            self.mounted.insert(name);
+        }
+        mounts
+    }
+
+    // 配置里写了但是没有挂载的插件,一般是没打开对应的 feature 或者名字写错了
+    pub fn report_missing(&self) {
+        for name in self.enabled.iter().flatten() {
+            if !self.mounted.contains(name) {
+                eprintln!("插件 {} 没有编译进来或不存在, 已跳过", name);
+            }
+        }
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().replace('-', "_")
+}
+
+fn read_plugins_file(path: &Path) -> Result<Option<PluginsFile>, Box<dyn Error + Send + Sync>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(toml::from_str(&std::fs::read_to_string(path)?)?))
+}
+
+// 和 kovi 的 build_bot! 挂载插件的方式相同,但只挂载配置里启用的插件;
+// 每个插件可以带 #[cfg(feature = "...")],没编译进来时整条跳过
+macro_rules! mount_plugins {
+    ($bot:ident, $list:ident, [$($(#[$meta:meta])* $plugin:ident),* $(,)?]) => {
+        $(
+            $(#[$meta])*
+            {
+                let (name, version) = $plugin::__kovi_get_plugin_info();
+                if $list.mounts(name) {
+                    println!("挂载插件: {}", name);
+                    $bot.mount_main(name, version, std::sync::Arc::new($plugin::__kovi_run_async_plugin));
+                }
+            }
+        )*
+        $list.report_missing();
+    };
+}
+
+pub(crate) use mount_plugins;
@@ -0,0 +1,206 @@
+// 测试共用的合成事件和启动机器人的辅助函数
+#![allow(dead_code)]
+
+use kovi::Message;
+use kovi::serde_json::Value;
+use onebot_mock::{ApiCall, MessageEvent, MockOneBot};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use tempfile::TempDir;
+
+pub use onebot_mock::event::{at, face, image, reply, text};
+
+pub const SELF_ID: i64 = 10000;
+pub const USER_ID: i64 = 20000;
+pub const GROUP_ID: i64 = 30000;
+pub const ADMIN_ID: i64 = 40000;
+// 启动时探测插件是否就绪用的群,测试里的消息不会发到这里
+pub const PROBE_GROUP_ID: i64 = 30001;
+pub const ACCESS_TOKEN: &str = "test-token";
+
+// 一条合成的消息,kovi 的 Message 和文本都从消息段算出
+pub struct SyntheticEvent {
+    pub event: MessageEvent,
+    pub message: Message,
+    pub text: Option<String>,
+    pub raw_message: String,
+    pub group_id: Option<i64>,
+}
+
+impl SyntheticEvent {
+    pub fn group(segments: Vec<Value>) -> Self {
+        Self::new(MessageEvent::group(SELF_ID, GROUP_ID, USER_ID, segments))
+    }
+
+    pub fn private(segments: Vec<Value>) -> Self {
+        Self::new(MessageEvent::private(SELF_ID, USER_ID, segments))
+    }
+
+    fn new(event: MessageEvent) -> Self {
+        let message: Message = kovi::serde_json::from_value(Value::Array(event.segments.clone()))
+            .expect("synthetic segments are valid");
+        Self {
+            text: event.text(),
+            raw_message: event.raw_message(),
+            group_id: event.group_id,
+            message,
+            event,
+        }
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn to_json(&self) -> Value {
+        self.event.to_json()
+    }
+}
+
+// 每种消息段都覆盖到的一组事件,群聊和私聊各一份
+pub fn all_kinds() -> Vec<SyntheticEvent> {
+    let cases = vec![
+        vec![text("运势 单张 今天顺利吗")],
+        vec![text("今日运势")],
+        vec![text("ai 你好")],
+        vec![text("chat 你好")],
+        vec![text("看看 https://example.com/page")],
+        vec![text("老鼠")],
+        vec![text("")],
+        vec![image("a.jpg")],
+        vec![face(178)],
+        vec![face(178), image("b.jpg")],
+        vec![reply(42)],
+        vec![reply(42), text("那感情呢")],
+        vec![reply(42), at(SELF_ID), text(" 运势")],
+        vec![at(SELF_ID)],
+        vec![at(3939271104), text(" 运势 单张")],
+        vec![at(SELF_ID), image("c.jpg"), text("语录")],
+    ];
+    cases
+        .into_iter()
+        .flat_map(|segments| [SyntheticEvent::group(segments.clone()), SyntheticEvent::private(segments)])
+        .collect()
+}
+
+// 在临时目录里运行机器人,连到模拟协议端;数据都写在临时目录,资源文件从仓库里的 assets 复制过去
+pub struct BotProcess {
+    child: Child,
+    pub dir: TempDir,
+}
+
+impl BotProcess {
+    pub fn spawn(mock: &MockOneBot, plugins: &[&str], envs: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(dir.path().join("kovi.conf.toml"), mock.kovi_conf(ADMIN_ID, &[])).expect("write kovi.conf.toml");
+        std::fs::write(dir.path().join("plugins.toml"), format!("plugins = {:?}\n", plugins)).expect("write plugins.toml");
+        copy_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"), &dir.path().join("assets")).expect("copy assets");
+
+        let child = Command::new(env!("CARGO_BIN_EXE_my-kovi-bot"))
+            .current_dir(dir.path())
+            // 没有密钥时各插件走离线逻辑,不会访问外网
+            .env("DEEPSEEK_API_KEY", "")
+            .env("BO_CHA_API_KEY", "")
+            .env("SILICON_FLOW_API_KEY", "")
+            .envs(envs.iter().copied())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("start bot");
+        Self { child, dir }
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for BotProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()))?;
+        } else {
+            std::fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+// 启动模拟协议端和挂载了指定插件的机器人,等两条连接都建立、插件都就绪
+pub async fn start_bot(plugins: &[&str], envs: &[(&str, &str)]) -> (MockOneBot, BotProcess) {
+    let mock = MockOneBot::start(SELF_ID, ACCESS_TOKEN).await.expect("start mock onebot");
+    let bot = BotProcess::spawn(&mock, plugins, envs);
+    assert!(mock.wait_connected(Duration::from_secs(30)).await, "机器人没有连上模拟协议端");
+    wait_ready(&mock, plugins).await;
+    (mock, bot)
+}
+
+// 每个插件一条没有副作用、一定会回复的命令
+fn readiness_probe(plugin: &str) -> Option<MessageEvent> {
+    let segments = match plugin {
+        "taro" => vec![text("运势 牌阵")],
+        "base" => vec![text("老鼠")],
+        "history-book" => vec![at(SELF_ID), text(" 语录 搜索")],
+        "deepseek" => return Some(MessageEvent::group(SELF_ID, PROBE_GROUP_ID, ADMIN_ID, vec![text("链接预览")])),
+        _ => return None,
+    };
+    Some(MessageEvent::group(SELF_ID, PROBE_GROUP_ID, USER_ID, segments))
+}
+
+pub fn is_probe_reply(call: &ApiCall) -> bool {
+    call.params["group_id"].as_i64() == Some(PROBE_GROUP_ID)
+}
+
+// 插件在连接建立后才注册处理函数,刚连上时推送的消息会被错过。
+// 探测命令发到单独的群,每个插件回复过一次才算就绪,之后测试里的每条消息只推送一次
+async fn wait_ready(mock: &MockOneBot, plugins: &[&str]) {
+    for plugin in plugins {
+        let Some(probe) = readiness_probe(plugin) else {
+            continue;
+        };
+        let ready = async {
+            loop {
+                mock.push_message(&probe);
+                if mock
+                    .wait_for_call(Duration::from_millis(500), |call| call.is_send() && is_probe_reply(call))
+                    .await
+                    .is_some()
+                {
+                    break;
+                }
+            }
+        };
+        assert!(
+            tokio::time::timeout(Duration::from_secs(30), ready).await.is_ok(),
+            "插件 {} 没有就绪",
+            plugin
+        );
+    }
+}
+
+// 等机器人发出下一条不是探测回复的消息
+pub async fn next_send(mock: &MockOneBot, timeout: Duration) -> Option<ApiCall> {
+    mock.wait_for_call(timeout, |call| call.is_send() && !is_probe_reply(call)).await
+}
+
+// 推送一次消息,等机器人的回复
+pub async fn ask_call(mock: &MockOneBot, event: &MessageEvent) -> Option<ApiCall> {
+    mock.push_message(event);
+    next_send(mock, Duration::from_secs(30)).await
+}
+
+// 只关心回复文本时用这个
+pub async fn ask(mock: &MockOneBot, event: &MessageEvent) -> Option<String> {
+    ask_call(mock, event).await.map(|call| call.text())
+}
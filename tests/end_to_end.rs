@@ -0,0 +1,79 @@
+// 通过模拟的 OneBot 协议端端到端地运行机器人
+mod common;
+
+use common::{
+    ADMIN_ID, GROUP_ID, SELF_ID, USER_ID, all_kinds, ask, ask_call, at, face, image, is_probe_reply, next_send, reply, start_bot, text,
+};
+use onebot_mock::MessageEvent;
+use std::time::Duration;
+
+#[tokio::test]
+async fn taro_replies_with_spread_help() {
+    let (mock, _bot) = start_bot(&["taro", "deepseek"], &[]).await;
+    let event = MessageEvent::group(SELF_ID, GROUP_ID, USER_ID, vec![text("运势 牌阵")]);
+    let reply = ask(&mock, &event).await.expect("没有收到回复");
+    assert!(reply.contains("可用牌阵"), "{}", reply);
+}
+
+#[tokio::test]
+async fn non_text_messages_do_not_crash_the_bot() {
+    let (mock, mut bot) = start_bot(&["taro", "deepseek"], &[]).await;
+    for segments in [vec![image("a.jpg")], vec![face(178)], vec![reply(42)], vec![face(1), image("b.jpg")]] {
+        mock.push_group_message(GROUP_ID, USER_ID, segments);
+    }
+    assert!(next_send(&mock, Duration::from_secs(2)).await.is_none());
+    assert!(bot.is_running());
+
+    // 之后的命令照常处理
+    let event = MessageEvent::group(SELF_ID, GROUP_ID, USER_ID, vec![text("运势 牌阵")]);
+    assert!(ask(&mock, &event).await.is_some());
+}
+
+// 每种消息段都交给挂载的全部插件的处理函数,机器人不能崩,之后照常回复
+#[tokio::test]
+async fn every_segment_goes_through_the_real_handlers() {
+    let (mock, mut bot) = start_bot(&["taro", "deepseek", "base", "history-book"], &[]).await;
+    for event in all_kinds() {
+        mock.push_message(&event.event);
+    }
+    mock.push_message(&MessageEvent::group(SELF_ID, GROUP_ID, USER_ID, vec![text("运势 牌阵")]));
+    let answered = mock
+        .wait_for_call(Duration::from_secs(30), |call| {
+            call.is_send() && !is_probe_reply(call) && call.text().contains("可用牌阵")
+        })
+        .await;
+    assert!(answered.is_some(), "处理完所有消息后没有再回复帮助");
+    assert!(bot.is_running());
+}
+
+#[tokio::test]
+async fn deepseek_admin_commands_need_admin() {
+    let (mock, _bot) = start_bot(&["taro", "deepseek"], &[]).await;
+    let event = MessageEvent::group(SELF_ID, GROUP_ID, ADMIN_ID, vec![text("链接预览")]);
+    let reply = ask(&mock, &event).await.expect("没有收到回复");
+    assert!(reply.contains("链接预览 开启"), "{}", reply);
+
+    mock.clear_calls();
+    mock.push_group_message(GROUP_ID, USER_ID, vec![text("链接预览")]);
+    assert!(next_send(&mock, Duration::from_secs(2)).await.is_none());
+}
+
+#[tokio::test]
+async fn plugins_follow_plugins_toml() {
+    let (mock, _bot) = start_bot(&["base"], &[]).await;
+    let event = MessageEvent::group(SELF_ID, GROUP_ID, USER_ID, vec![text("老鼠")]);
+    let call = ask_call(&mock, &event).await.expect("没有收到回复");
+    assert!(!call.segments("image").is_empty(), "{:?}", call);
+
+    // 没有挂载 taro,运势命令没有回复
+    mock.push_group_message(GROUP_ID, USER_ID, vec![text("运势 牌阵")]);
+    assert!(next_send(&mock, Duration::from_secs(2)).await.is_none());
+}
+
+#[tokio::test]
+async fn history_book_answers_when_listed() {
+    let (mock, _bot) = start_bot(&["history-book"], &[]).await;
+    let event = MessageEvent::group(SELF_ID, GROUP_ID, USER_ID, vec![at(SELF_ID), text(" 语录")]);
+    let reply = ask(&mock, &event).await.expect("没有收到回复");
+    assert!(reply.contains("本群还没有语录"), "{}", reply);
+}
@@ -0,0 +1,230 @@
+// 各插件的路由函数: 任意消息段都不能 panic,命令分到正确的处理函数。
+// 这里只调用路由函数,处理函数本身在 end_to_end 里通过模拟协议端跑
+mod common;
+
+use common::{GROUP_ID, SELF_ID, SyntheticEvent, all_kinds, at, face, image, reply, text};
+
+#[test]
+fn no_router_panics_on_any_segment() {
+    for event in all_kinds() {
+        let json = event.to_json();
+        let result = std::panic::catch_unwind(|| {
+            base::wants_mouse(event.text());
+            deepseek::route(event.text(), event.group_id);
+            deepseek::route_admin(event.text());
+            taro::route(event.text(), &event.raw_message, &event.message);
+            history_book::route(event.text(), &event.message, SELF_ID, event.group_id);
+        });
+        assert!(result.is_ok(), "路由 panic: {}", json);
+    }
+}
+
+#[test]
+fn taro_ignores_messages_without_text() {
+    for segments in [vec![image("a.jpg")], vec![face(1)], vec![reply(42)], vec![at(SELF_ID)]] {
+        let event = SyntheticEvent::group(segments);
+        assert_eq!(taro::route(event.text(), &event.raw_message, &event.message), None);
+    }
+}
+
+#[test]
+fn taro_routes_commands() {
+    let event = SyntheticEvent::group(vec![text("运势 单张 今天顺利吗")]);
+    assert_eq!(
+        taro::route(event.text(), &event.raw_message, &event.message),
+        Some(taro::Route::Reading("单张 今天顺利吗".to_string()))
+    );
+
+    let event = SyntheticEvent::group(vec![text("今日运势 排行")]);
+    assert_eq!(
+        taro::route(event.text(), &event.raw_message, &event.message),
+        Some(taro::Route::Daily("排行".to_string()))
+    );
+
+    let event = SyntheticEvent::group(vec![reply(42), text("那感情呢")]);
+    assert_eq!(
+        taro::route(event.text(), &event.raw_message, &event.message),
+        Some(taro::Route::Reply("那感情呢".to_string()))
+    );
+
+    // 不是回复也不是命令的普通消息
+    let event = SyntheticEvent::group(vec![text("那感情呢")]);
+    assert_eq!(taro::route(event.text(), &event.raw_message, &event.message), None);
+
+    let event = SyntheticEvent::group(vec![at(3939271104), text(" 运势 单张")]);
+    assert_eq!(taro::route(event.text(), &event.raw_message, &event.message), None);
+}
+
+#[test]
+fn deepseek_routes_commands() {
+    assert_eq!(
+        deepseek::route(Some("ai 你好"), Some(GROUP_ID)),
+        Some(deepseek::Route::Ai("你好".to_string()))
+    );
+    assert_eq!(
+        deepseek::route(Some("chat 你好"), None),
+        Some(deepseek::Route::Chat("你好".to_string()))
+    );
+    assert_eq!(
+        deepseek::route(Some("看看 https://example.com/page"), Some(GROUP_ID)),
+        Some(deepseek::Route::LinkPreview {
+            group_id: GROUP_ID,
+            url: "https://example.com/page".to_string()
+        })
+    );
+    // 私聊和链接预览命令本身不触发预览
+    assert_eq!(deepseek::route(Some("看看 https://example.com/page"), None), None);
+    assert_eq!(deepseek::route(Some("链接预览 开启 https://example.com"), Some(GROUP_ID)), None);
+    assert_eq!(deepseek::route(None, Some(GROUP_ID)), None);
+
+    assert_eq!(
+        deepseek::route_admin(Some("知识库 列表")),
+        Some(deepseek::AdminRoute::Knowledge("列表"))
+    );
+    assert_eq!(deepseek::route_admin(None), None);
+}
+
+#[test]
+fn base_only_answers_exact_text() {
+    assert!(base::wants_mouse(Some("老鼠")));
+    assert!(!base::wants_mouse(Some("老鼠们")));
+    assert!(!base::wants_mouse(None));
+}
+
+#[test]
+fn history_book_saves_only_when_mentioned_in_group() {
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录"), image("a.jpg")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Save {
+            group_id: GROUP_ID,
+            quoted_id: None,
+            caption: String::new()
+        })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 "), at(55555), text(" 名场面"), image("a.jpg")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Save {
+            group_id: GROUP_ID,
+            quoted_id: Some(55555),
+            caption: "名场面".to_string()
+        })
+    );
+
+    for event in [
+        SyntheticEvent::group(vec![text("语录"), image("a.jpg")]),
+        SyntheticEvent::group(vec![at(3939271104), text(" 语录"), image("a.jpg")]),
+        SyntheticEvent::group(vec![at(SELF_ID), image("a.jpg")]),
+        SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 收录")]),
+        SyntheticEvent::private(vec![at(SELF_ID), text(" 语录"), image("a.jpg")]),
+    ] {
+        assert_eq!(history_book::route(event.text(), &event.message, SELF_ID, event.group_id), None);
+    }
+}
+
+#[test]
+fn history_book_routes_lookups() {
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Random { group_id: GROUP_ID, quoted_id: None })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 "), at(55555)]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Random { group_id: GROUP_ID, quoted_id: Some(55555) })
+    );
+
+    // 回复时客户端会自动 @ 原作者
+    let event = SyntheticEvent::group(vec![reply(42), at(55555), at(SELF_ID), text(" 语录")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::SaveReply {
+            group_id: GROUP_ID,
+            message_id: 42,
+            caption: String::new()
+        })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 搜索 摸鱼 ")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Search {
+            group_id: GROUP_ID,
+            keyword: "摸鱼".to_string()
+        })
+    );
+}
+
+#[test]
+fn history_book_routes_moderation() {
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 删除 #12")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Delete {
+            group_id: GROUP_ID,
+            quote_id: Some(12)
+        })
+    );
+
+    // 回复着机器人发的语录删除时不收录被回复的消息
+    let event = SyntheticEvent::group(vec![reply(42), at(SELF_ID), text(" 语录 删除 十二")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Delete {
+            group_id: GROUP_ID,
+            quote_id: None
+        })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 通过 3")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Approve {
+            group_id: GROUP_ID,
+            quote_id: Some(3)
+        })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 审核 开启")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::ApprovalMode {
+            group_id: GROUP_ID,
+            enabled: Some(true)
+        })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 待审")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Pending { group_id: GROUP_ID })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 删除记录")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::DeletionLog { group_id: GROUP_ID })
+    );
+}
+
+#[test]
+fn history_book_routes_archive_commands() {
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 导出")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Export { group_id: GROUP_ID })
+    );
+
+    let event = SyntheticEvent::group(vec![at(SELF_ID), text(" 语录 导入 backup/语录.tar ")]);
+    assert_eq!(
+        history_book::route(event.text(), &event.message, SELF_ID, event.group_id),
+        Some(history_book::Route::Import {
+            group_id: GROUP_ID,
+            path: "backup/语录.tar".to_string()
+        })
+    );
+}